@@ -0,0 +1,56 @@
+// The IDL instructions that `#[program]` generates at the crate root still call the
+// deprecated `AccountInfo::realloc`; lint attributes can't reach that generated code
+// any more narrowly than this.
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+declare_id!("CFpjPQ4yLnsFwRoqGjowgQFWLnFoBPMR24vHjKyu1gJB");
+
+#[program]
+pub mod mock_multisig {
+    use super::*;
+
+    /// ✅ Forward `data` to `target_program` with the `vault` PDA signing, the way a
+    /// Squads-style multisig executes an approved transaction
+    /// - `remaining_accounts`: the forwarded instruction's accounts, in its order; the
+    ///   vault is marked as a signer wherever it appears
+    pub fn execute<'info>(
+        ctx: Context<'_, '_, '_, 'info, Execute<'info>>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let vault = ctx.accounts.vault.key();
+        let ix = Instruction {
+            program_id: ctx.accounts.target_program.key(),
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|acc| AccountMeta {
+                    pubkey: acc.key(),
+                    is_signer: acc.is_signer || acc.key() == vault,
+                    is_writable: acc.is_writable,
+                })
+                .collect(),
+            data,
+        };
+
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.vault.to_account_info());
+        infos.push(ctx.accounts.target_program.to_account_info());
+
+        let seeds: &[&[u8]] = &[b"vault", &[ctx.bumps.vault]];
+        invoke_signed(&ix, &infos, &[seeds])?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    #[account(mut, seeds = [b"vault"], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(executable)]
+    /// CHECK: any program the vault acts on
+    pub target_program: UncheckedAccount<'info>,
+}
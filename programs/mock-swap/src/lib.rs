@@ -0,0 +1,74 @@
+// The IDL instructions that `#[program]` generates at the crate root still call the
+// deprecated `AccountInfo::realloc`; lint attributes can't reach that generated code
+// any more narrowly than this.
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+declare_id!("HvhMjyxnKmTyNuAoNumKWu8VrUz3VCF4YsykbSYXxBhW");
+
+/// Output tokens paid per input token
+pub const RATE: u64 = 2;
+
+#[program]
+pub mod mock_swap {
+    use super::*;
+
+    /// ✅ Swap `amount_in` from `source` for `amount_in * RATE` out of the pool
+    /// - Matches the swap CPI boundary `distribute_swapped` calls into
+    pub fn swap(ctx: Context<Swap>, amount_in: u64, minimum_amount_out: u64) -> Result<()> {
+        let amount_out = amount_in
+            .checked_mul(RATE)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        require!(
+            amount_out >= minimum_amount_out,
+            MockSwapError::SlippageExceeded
+        );
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.source.to_account_info(),
+            to: ctx.accounts.pool_in.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount_in)?;
+
+        let seeds: &[&[u8]] = &[b"pool", &[ctx.bumps.pool_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.pool_out.to_account_info(),
+            to: ctx.accounts.destination.to_account_info(),
+            authority: ctx.accounts.pool_auth.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount_out)
+    }
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(mut)]
+    pub source: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    #[account(mut, constraint = pool_in.owner == pool_auth.key())]
+    pub pool_in: Account<'info, TokenAccount>,
+    #[account(mut, constraint = pool_out.owner == pool_auth.key())]
+    pub pool_out: Account<'info, TokenAccount>,
+    #[account(seeds = [b"pool"], bump)]
+    /// CHECK: PDA authority over the pool accounts
+    pub pool_auth: AccountInfo<'info>,
+}
+
+#[error_code]
+pub enum MockSwapError {
+    #[msg("Swap output below the minimum amount out")]
+    SlippageExceeded,
+}
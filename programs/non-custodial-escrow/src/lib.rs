@@ -1,98 +1,986 @@
+// The IDL instructions that `#[program]` generates at the crate root still call the
+// deprecated `AccountInfo::realloc`; lint attributes can't reach that generated code
+// any more narrowly than this.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::instruction::{
+    get_stack_height, AccountMeta, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::{ed25519_program, hash, sysvar};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::{self, spl_token::native_mint};
+use anchor_spl::token_interface::{
+    self, Burn, CloseAccount, InitializeAccount3, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+use solana_keccak_hasher as keccak;
 
 declare_id!("9obCENSCc25Fw6ca4WZNUXQfhYM9xymQGAPkNc5Udsec");
 
+/// `EscrowState` layout version written by `initialize`. Version 2 escrows were written
+/// once every path kept the per-slot vectors in sync with `contributors`
+pub const ESCROW_VERSION: u8 = 2;
+
+/// Oldest layout version instructions still accept
+pub const MIN_ESCROW_VERSION: u8 = 1;
+
+/// Maximum number of records an `AuditLog` can hold
+pub const MAX_AUDIT_RECORDS: usize = 8;
+
+/// Highest `burn_bps` an escrow may configure (50%)
+pub const MAX_BURN_BPS: u16 = 5_000;
+
+/// Deposit per slot set by `initialize`; adjustable with `set_required_deposit`
+pub const DEFAULT_REQUIRED_DEPOSIT: u64 = 5;
+
+/// Decimals of the USD value returned by `vault_usd_value` (micro-dollars)
+pub const USD_VALUE_DECIMALS: i32 = 6;
+
+/// Most category tags an escrow may carry
+pub const MAX_TAGS: usize = 8;
+
+/// Most extra mints an escrow may accept deposits in, besides `token_mint`
+pub const MAX_ACCEPTED_MINTS: usize = 4;
+
+/// Most open escrows an owner with an `OwnerRegistry` may hold at once
+pub const MAX_ESCROWS_PER_OWNER: u32 = 16;
+
+/// Highest distribution mode this build understands; anything above is `UnknownMode`
+pub const MAX_KNOWN_MODE: u8 = 19;
+
+/// Longest referral code `register_referral_code` accepts
+pub const MAX_REFERRAL_CODE_LEN: usize = 16;
+
+/// Most decimals an escrow mint may have; anything above is a misconfigured mint
+pub const MAX_MINT_DECIMALS: u8 = 18;
+
+/// Most wallets a `target_whitelist` may name (one per contributor slot)
+pub const MAX_TARGET_WHITELIST: usize = 5;
+
+/// Layout version that may carry per-slot vectors shorter than `contributors` (written
+/// before every path kept them in sync); the only one `repair_vectors` accepts
+pub const REPAIRABLE_VERSION: u8 = 1;
+
+/// Most escrows one `batch_vault_info` call reports: 40 bytes each keeps the result
+/// inside the 1024-byte return data limit
+pub const MAX_BATCH_VAULT_INFO: usize = 25;
+
+/// Most entries `mode_history` keeps; the oldest is dropped once it's full
+pub const MAX_MODE_HISTORY: usize = 8;
+
 #[program]
 pub mod bonk_escrow_final {
-    use anchor_spl::associated_token::get_associated_token_address;
-
     use super::*;
 
     /// ✅ Initialize escrow with a unique name
-    pub fn initialize(ctx: Context<Initialize>, name: String) -> Result<()> {
-        require!(name.len() <= 32, EscrowError::NameTooLong);
+    /// - `fallback_sweep`: optional wallet that receives the vault after `sweep_after_secs` of inactivity
+    /// - `owner_can_win`: whether the owner may be picked as winner (modes 0 and 12)
+    /// - `independent_owner`: owner may not deposit at all nor be any mode's target;
+    ///   takes precedence over `owner_can_win`
+    /// - `attester`: when set, depositors need an unexpired `Attestation` from this key
+    /// - `gate_mint`: when set, depositors must hold at least `gate_min_balance` of this mint
+    /// - `close_on_distribute`: close the vault (rent to the owner) once a distribution empties it
+    /// - `merkle_root`: recipients proven against this root can `claim_with_proof`
+    ///   (all zeroes disables claims)
+    /// - `burn_bps`: share of the vault burned at distribution, at most 5000 (50%)
+    /// - `fullness_mode`: pool is full at 5 funded slots (`Count`) or once deposits
+    ///   reach `target_total` (`Total`, which needs a non-zero target)
+    /// - `auto_mode`: when set, the deposit that fills the pool also distributes it with
+    ///   this mode and `auto_target` (not combinable with `burn_bps`, `close_on_distribute`,
+    ///   `opt_in_required`, `dust_to_owner` or `min_unique_payers`)
+    /// - `distribute_fallback_secs`: how long a full pool may wait on the owner before any
+    ///   contributor can `fallback_distribute` it (0 disables the fallback)
+    /// - `allowed_modes`: bit `m` permits distribution mode `m`; 0 permits every mode
+    /// - `min_deposit_interval_slots`: slots that must pass between consecutive deposits,
+    ///   so one actor can't fill every slot in a single block (0 disables)
+    /// - `is_nft`: the mint is an NFT (0 decimals, supply 1) deposited as a single unit;
+    ///   needs `Total` fullness with a `target_total` of 1 and only mode 0 may pay it out
+    /// - `price_oracle`: Pyth price account valuing the escrow mint in USD for
+    ///   `vault_usd_value`; prices older than `oracle_max_age_secs` are rejected
+    /// - `tags`: operator-defined category ids (at most `MAX_TAGS`) for grouping escrows;
+    ///   metadata only, announced in `TagsSet` for indexers
+    /// - `min_account_age_slots`: depositors' wallets must be at least this many slots old
+    ///   (0 disables). Solana doesn't record when an account was created, so the age comes
+    ///   from the `first_seen_slot` the escrow's `attester` vouched for; needs an attester
+    /// - `accepted_mints`: further mints (at most `MAX_ACCEPTED_MINTS`) a slot may be
+    ///   funded in, counted 1:1 in base units with `token_mint`; each lands in the vault
+    ///   authority's ATA for that mint. Not available for NFT escrows
+    /// - `opt_in_required`: `distribute` only pays recipients who called `opt_in`; the
+    ///   other shares are held for `distribute_held`. Other distribution paths refuse
+    ///   such escrows
+    /// - `min_payout`: least any recipient of the time-weighted mode 14 is paid, topped
+    ///   up from the largest shares (0 disables)
+    /// - `dust_to_owner`: `distribute` sends whatever its split leaves in the vault to
+    ///   the owner's `owner_ata`, so the vault ends empty (shares held for opt-in stay)
+    /// - `reinit_cooldown_secs`: once closed, this escrow's address can't be initialized
+    ///   again for this long (0 disables); `close_escrow` records it in a tombstone
+    /// - `owner_is_pda`: the owner is a program-derived address, e.g. a multisig vault.
+    ///   A PDA has no key to sign with; its program signs for it with `invoke_signed`,
+    ///   and the runtime then marks it as a signer of our instruction, so every owner
+    ///   check works unchanged. Setting this asserts that arrangement: `initialize` must
+    ///   itself arrive through CPI, else `OwnerNotPda`
+    /// - `target_whitelist`: when non-empty, the only wallets (at most
+    ///   `MAX_TARGET_WHITELIST`) mode 0 may pay out to, including `auto_target`
+    /// - `min_unique_payers`: distinct wallets that must have funded the live slots before
+    ///   any distribution (0 disables). The payer is whoever authorized the transfer: the
+    ///   contributor, or the delegate for `deposit_delegated`. Not combinable with `auto_mode`
+    /// - `deposit_deadline`: unix time after which no new slot is funded, until the owner
+    ///   calls `extend_deadline` (0 for none)
+    ///
+    /// Passing the owner's `owner_registry` counts the escrow against `MAX_ESCROWS_PER_OWNER`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        name: String,
+        fallback_sweep: Option<Pubkey>,
+        sweep_after_secs: i64,
+        owner_can_win: bool,
+        independent_owner: bool,
+        attester: Option<Pubkey>,
+        gate_mint: Option<Pubkey>,
+        gate_min_balance: u64,
+        close_on_distribute: bool,
+        merkle_root: [u8; 32],
+        burn_bps: u16,
+        fullness_mode: FullnessMode,
+        target_total: u64,
+        auto_mode: Option<u8>,
+        auto_target: Pubkey,
+        distribute_fallback_secs: i64,
+        allowed_modes: u16,
+        min_deposit_interval_slots: u64,
+        is_nft: bool,
+        price_oracle: Option<Pubkey>,
+        oracle_max_age_secs: i64,
+        tags: Vec<u32>,
+        min_account_age_slots: u64,
+        accepted_mints: Vec<Pubkey>,
+        opt_in_required: bool,
+        min_payout: u64,
+        dust_to_owner: bool,
+        reinit_cooldown_secs: i64,
+        owner_is_pda: bool,
+        target_whitelist: Vec<Pubkey>,
+        min_unique_payers: u8,
+        deposit_deadline: i64,
+    ) -> Result<()> {
+        require!(burn_bps <= MAX_BURN_BPS, EscrowError::InvalidBps);
+        require!(tags.len() <= MAX_TAGS, EscrowError::TooManyTags);
+        require!(
+            accepted_mints.len() <= MAX_ACCEPTED_MINTS
+                && (!is_nft || accepted_mints.is_empty())
+                && accepted_mints.iter().enumerate().all(|(i, m)| {
+                    *m != ctx.accounts.mint.key() && !accepted_mints[..i].contains(m)
+                }),
+            EscrowError::InvalidAcceptedMints
+        );
+        require!(
+            min_account_age_slots == 0 || attester.is_some(),
+            EscrowError::AgeCheckNeedsAttester
+        );
+        require!(
+            fullness_mode == FullnessMode::Count || target_total > 0,
+            EscrowError::InvalidTargetTotal
+        );
+        // ✅ `deposit` carries neither the mint nor the owner, so it can't burn or close the
+        // vault, and it has nowhere to hold back shares for recipients who haven't opted in
+        // nor to send the dust; a fill short of unique payers would fail the deposit itself
+        require!(
+            auto_mode.is_none()
+                || (burn_bps == 0
+                    && !close_on_distribute
+                    && !opt_in_required
+                    && !dust_to_owner
+                    && min_unique_payers == 0),
+            EscrowError::AutoDistributeUnsupported
+        );
+        require!(min_unique_payers <= 5, EscrowError::InvalidMinUniquePayers);
+        check_mint(&ctx.accounts.mint)?;
+        check_tombstone(&ctx.accounts.tombstone)?;
+        require!(
+            !owner_is_pda || get_stack_height() > TRANSACTION_LEVEL_STACK_HEIGHT,
+            EscrowError::OwnerNotPda
+        );
+        require!(
+            target_whitelist.len() <= MAX_TARGET_WHITELIST,
+            EscrowError::InvalidTargetWhitelist
+        );
+        require!(
+            auto_mode != Some(0)
+                || target_whitelist.is_empty()
+                || target_whitelist.contains(&auto_target),
+            EscrowError::TargetNotWhitelisted
+        );
+        check_auto_mode(auto_mode, allowed_modes)?;
+
+        if is_nft {
+            let mint = &ctx.accounts.mint;
+            require!(
+                mint.decimals == 0 && mint.supply == 1,
+                EscrowError::NotAnNft
+            );
+            require!(
+                fullness_mode == FullnessMode::Total && target_total == 1,
+                EscrowError::InvalidTargetTotal
+            );
+        }
+
+        let esc = &mut ctx.accounts.escrow;
+        init_escrow_state(
+            esc,
+            ctx.accounts.owner.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+            name,
+        )?;
+        esc.fallback_sweep = fallback_sweep;
+        esc.sweep_after_secs = sweep_after_secs;
+        esc.owner_can_win = owner_can_win;
+        esc.independent_owner = independent_owner;
+        esc.attester = attester;
+        esc.gate_mint = gate_mint;
+        esc.gate_min_balance = gate_min_balance;
+        esc.close_on_distribute = close_on_distribute;
+        esc.merkle_root = merkle_root;
+        esc.burn_bps = burn_bps;
+        esc.fullness_mode = fullness_mode;
+        esc.target_total = target_total;
+        esc.auto_mode = auto_mode;
+        esc.auto_target = auto_target;
+        esc.distribute_fallback_secs = distribute_fallback_secs;
+        esc.allowed_modes = allowed_modes;
+        esc.min_deposit_interval_slots = min_deposit_interval_slots;
+        esc.is_nft = is_nft;
+        esc.price_oracle = price_oracle;
+        esc.oracle_max_age_secs = oracle_max_age_secs;
+        esc.tags = tags.clone();
+        esc.min_account_age_slots = min_account_age_slots;
+        esc.accepted_mints = accepted_mints;
+        esc.opt_in_required = opt_in_required;
+        esc.min_payout = min_payout;
+        esc.dust_to_owner = dust_to_owner;
+        esc.reinit_cooldown_secs = reinit_cooldown_secs;
+        esc.owner_is_pda = owner_is_pda;
+        esc.target_whitelist = target_whitelist;
+        esc.min_unique_payers = min_unique_payers;
+        esc.deposit_deadline = deposit_deadline;
+        register_escrow(esc, ctx.accounts.owner_registry.as_mut())?;
+        if is_nft {
+            esc.required_deposit = 1;
+        }
+
+        emit!(TagsSet {
+            escrow: esc.key(),
+            tags,
+        });
+        Ok(())
+    }
+
+    /// ✅ Opt in to counting the owner's escrows (at most `MAX_ESCROWS_PER_OWNER` open
+    /// at once); escrows created before the registry aren't counted
+    pub fn init_owner_registry(ctx: Context<InitOwnerRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.owner_registry;
+        registry.owner = ctx.accounts.owner.key();
+        registry.escrow_count = 0;
+        Ok(())
+    }
+
+    /// ✅ Save a reusable set of `initialize` settings under `template_name`
+    /// - The pool's shape, sweep, access gates and mode settings (`allowed_modes`,
+    ///   `auto_mode` / `auto_target`, checked as `initialize` checks them), plus the
+    ///   per-slot `required_deposit`
+    /// - Left out on purpose, as they describe one pool rather than an operator's policy:
+    ///   `merkle_root`, `tags`, `target_whitelist`, `accepted_mints`, `is_nft`,
+    ///   `price_oracle` / `oracle_max_age_secs`, `owner_is_pda` and `deposit_deadline`
+    /// - Also left out, so templated escrows start with them disabled: `distribute_fallback_secs`,
+    ///   `min_deposit_interval_slots`, `min_account_age_slots`, `opt_in_required`,
+    ///   `min_payout`, `dust_to_owner`, `reinit_cooldown_secs` and `min_unique_payers`;
+    ///   an escrow that needs them is created with `initialize`
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_template(
+        ctx: Context<CreateTemplate>,
+        template_name: String,
+        fallback_sweep: Option<Pubkey>,
+        sweep_after_secs: i64,
+        owner_can_win: bool,
+        independent_owner: bool,
+        attester: Option<Pubkey>,
+        gate_mint: Option<Pubkey>,
+        gate_min_balance: u64,
+        required_deposit: u64,
+        close_on_distribute: bool,
+        burn_bps: u16,
+        fullness_mode: FullnessMode,
+        target_total: u64,
+        allowed_modes: u16,
+        auto_mode: Option<u8>,
+        auto_target: Pubkey,
+    ) -> Result<()> {
+        require!(template_name.len() <= 32, EscrowError::NameTooLong);
+        require!(required_deposit > 0, EscrowError::InvalidDepositAmount);
+        require!(burn_bps <= MAX_BURN_BPS, EscrowError::InvalidBps);
+        require!(
+            fullness_mode == FullnessMode::Count || target_total > 0,
+            EscrowError::InvalidTargetTotal
+        );
+        require!(
+            auto_mode.is_none() || (burn_bps == 0 && !close_on_distribute),
+            EscrowError::AutoDistributeUnsupported
+        );
+        check_auto_mode(auto_mode, allowed_modes)?;
+
+        let template = &mut ctx.accounts.template;
+        template.operator = ctx.accounts.operator.key();
+        template.fallback_sweep = fallback_sweep;
+        template.sweep_after_secs = sweep_after_secs;
+        template.owner_can_win = owner_can_win;
+        template.independent_owner = independent_owner;
+        template.attester = attester;
+        template.gate_mint = gate_mint;
+        template.gate_min_balance = gate_min_balance;
+        template.required_deposit = required_deposit;
+        template.close_on_distribute = close_on_distribute;
+        template.burn_bps = burn_bps;
+        template.fullness_mode = fullness_mode;
+        template.target_total = target_total;
+        template.allowed_modes = allowed_modes;
+        template.auto_mode = auto_mode;
+        template.auto_target = auto_target;
+        Ok(())
+    }
 
+    /// ✅ Initialize an escrow with the settings of one of the owner's templates
+    pub fn initialize_from_template(
+        ctx: Context<InitializeFromTemplate>,
+        name: String,
+        template_name: String,
+    ) -> Result<()> {
+        msg!("Initializing from template {}", template_name);
+        check_mint(&ctx.accounts.mint)?;
+        check_tombstone(&ctx.accounts.tombstone)?;
+        let tpl = &ctx.accounts.template;
         let esc = &mut ctx.accounts.escrow;
-        esc.owner = ctx.accounts.owner.key();
-        esc.token_mint = ctx.accounts.mint.key();
-        esc.contributors = vec![];
-        esc.distributed = false;
-        esc.name = name;
+        init_escrow_state(
+            esc,
+            ctx.accounts.owner.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+            name,
+        )?;
+        esc.fallback_sweep = tpl.fallback_sweep;
+        esc.sweep_after_secs = tpl.sweep_after_secs;
+        esc.owner_can_win = tpl.owner_can_win;
+        esc.independent_owner = tpl.independent_owner;
+        esc.attester = tpl.attester;
+        esc.gate_mint = tpl.gate_mint;
+        esc.gate_min_balance = tpl.gate_min_balance;
+        esc.required_deposit = tpl.required_deposit;
+        esc.close_on_distribute = tpl.close_on_distribute;
+        esc.burn_bps = tpl.burn_bps;
+        esc.fullness_mode = tpl.fullness_mode;
+        esc.target_total = tpl.target_total;
+        esc.allowed_modes = tpl.allowed_modes;
+        esc.auto_mode = tpl.auto_mode;
+        esc.auto_target = tpl.auto_target;
+        register_escrow(esc, ctx.accounts.owner_registry.as_mut())?;
 
         Ok(())
     }
 
-    /// ✅ Deposit exactly 5 tokens; max 5 contributors allowed
-    pub fn deposit(ctx: Context<Deposit>, name: String, amount: u64) -> Result<()> {
+    /// ✅ Attester vouches for `subject` until `expires_at` (re-callable to renew)
+    /// - `first_seen_slot`: slot the attester first saw the wallet active, for escrows
+    ///   with `min_account_age_slots` (0 when not vouched for)
+    pub fn attest(
+        ctx: Context<Attest>,
+        subject: Pubkey,
+        expires_at: i64,
+        first_seen_slot: u64,
+    ) -> Result<()> {
+        let att = &mut ctx.accounts.attestation;
+        att.attester = ctx.accounts.attester.key();
+        att.subject = subject;
+        att.expires_at = expires_at;
+        att.first_seen_slot = first_seen_slot;
+        Ok(())
+    }
+
+    /// ✅ Deposit `required_deposit` tokens; max 5 contributors allowed
+    /// - Offering more only pulls what the slot needs, the surplus stays put
+    /// - `idempotent`: a resubmitted deposit (same contributor, and the amount and mint the
+    ///   slot recorded) succeeds as a no-op
+    /// - `memo`: optional note kept with the slot (up to 32 bytes, empty for none)
+    /// - With `auto_mode` set, the deposit that fills the pool distributes it when the
+    ///   recipient ATAs are passed in `remaining_accounts` (as for `distribute`; no audit
+    ///   record is written); without them the pool is left full for a manual `distribute`
+    pub fn deposit<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Deposit<'info>>,
+        name: String,
+        amount: u64,
+        idempotent: bool,
+        memo: String,
+    ) -> Result<()> {
         let esc = &mut ctx.accounts.escrow;
+        let mint = ctx.accounts.mint.key();
 
         require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        // ✅ Each accepted mint has its own vault: the vault authority's ATA for that mint
         require!(
-            esc.contributors.len() < 5,
-            EscrowError::MaxContributorsReached
+            ctx.accounts.vault_ata.key()
+                == get_associated_token_address_with_program_id(
+                    ctx.accounts.vault_auth.key,
+                    &mint,
+                    ctx.accounts.token_program.key
+                ),
+            EscrowError::InvalidVault
+        );
+        // ✅ The vault authority only ever signs for payouts, never as a depositor
+        require!(
+            ctx.accounts.contributor.key() != ctx.accounts.vault_auth.key(),
+            EscrowError::InvalidContributor
+        );
+
+        // ✅ Retried transaction: nothing left to do if this deposit already landed. Only
+        // the amount and mint the slot recorded count as the same request
+        if esc.has_deposited(ctx.accounts.contributor.key) {
+            let i = esc
+                .contributors
+                .iter()
+                .position(|c| c == ctx.accounts.contributor.key)
+                .ok_or(EscrowError::NotAContributor)?;
+            require!(
+                idempotent && amount == esc.amounts[i] && mint == esc.deposit_mints[i],
+                EscrowError::AlreadyDeposited
+            );
+            return Ok(());
+        }
+
+        check_attestation(
+            esc,
+            ctx.accounts.contributor.key(),
+            ctx.accounts.attestation.as_ref(),
+        )?;
+        check_gate(
+            esc,
+            ctx.accounts.contributor.key(),
+            ctx.accounts.gate_ata.as_ref(),
+        )?;
+        require!(memo.len() <= 32, EscrowError::MemoTooLong);
+        let needed = admit_contributor(
+            esc,
+            ctx.accounts.contributor.key(),
+            ctx.accounts.contributor.key(),
+            mint,
+            amount,
+            memo.clone(),
+        )?;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.contributor_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.contributor.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, needed, ctx.accounts.mint.decimals)?;
+
+        emit!(DepositMade {
+            escrow: ctx.accounts.escrow.key(),
+            contributor: ctx.accounts.contributor.key(),
+            amount: needed,
+            memo,
+        });
+
+        let esc = &ctx.accounts.escrow;
+        let Some(mode) = esc.auto_mode else {
+            return Ok(());
+        };
+        if !esc.is_full() || ctx.remaining_accounts.is_empty() {
+            return Ok(());
+        }
+        check_distributable(esc, mode)?;
+
+        // ✅ Only a vault owned by `vault_auth` lets the signed payouts below through
+        ctx.accounts.vault_ata.reload()?;
+        let payouts = distribution_plan(
+            esc,
+            ctx.accounts.vault_ata.amount,
+            mode,
+            esc.auto_target,
+            0,
+            0,
+        )?;
+        check_committed_plan(esc, mode, esc.auto_target, 0, 0, &payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        ctx.accounts.escrow.record_mode(mode);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Deposit on behalf of a token account owner through an approved delegate
+    /// - The recorded contributor is the token account's owner, not the delegate
+    pub fn deposit_delegated(
+        ctx: Context<DepositDelegated>,
+        name: String,
+        amount: u64,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let source = &ctx.accounts.contributor_ata;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(
+            source.owner != ctx.accounts.vault_auth.key(),
+            EscrowError::InvalidContributor
+        );
+        require!(
+            !esc.has_deposited(&source.owner),
+            EscrowError::AlreadyDeposited
+        );
+
+        check_attestation(esc, source.owner, ctx.accounts.attestation.as_ref())?;
+        check_gate(esc, source.owner, ctx.accounts.gate_ata.as_ref())?;
+        let mint = esc.token_mint;
+        let needed = admit_contributor(
+            esc,
+            source.owner,
+            ctx.accounts.delegate.key(),
+            mint,
+            amount,
+            String::new(),
+        )?;
+        require!(
+            source.delegate == COption::Some(ctx.accounts.delegate.key())
+                && source.delegated_amount >= needed,
+            EscrowError::InsufficientDelegation
+        );
+
+        let cpi_accounts = TransferChecked {
+            from: source.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.delegate.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, needed, ctx.accounts.mint.decimals)
+    }
+
+    /// ✅ Claim `code` (1-16 ASCII letters and digits) so depositors can name the signer
+    /// as their referrer with `deposit_with_code`
+    pub fn register_referral_code(ctx: Context<RegisterReferralCode>, code: String) -> Result<()> {
+        require!(
+            !code.is_empty()
+                && code.len() <= MAX_REFERRAL_CODE_LEN
+                && code.bytes().all(|b| b.is_ascii_alphanumeric()),
+            EscrowError::InvalidReferralCode
+        );
+
+        let referral = &mut ctx.accounts.referral_code;
+        referral.referrer = ctx.accounts.referrer.key();
+        referral.code = code;
+        Ok(())
+    }
+
+    /// ✅ `deposit` naming a referrer by code; the referrer the code resolves to is
+    /// recorded with the slot. Never auto-distributes: a pool it fills waits for `distribute`
+    pub fn deposit_with_code(
+        ctx: Context<DepositWithCode>,
+        name: String,
+        amount: u64,
+        code: String,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let contributor = ctx.accounts.contributor.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(
+            contributor != ctx.accounts.vault_auth.key(),
+            EscrowError::InvalidContributor
         );
         require!(
-            !esc.contributors.contains(ctx.accounts.contributor.key),
+            !esc.has_deposited(&contributor),
             EscrowError::AlreadyDeposited
         );
-        require!(amount == 5, EscrowError::InvalidDepositAmount);
 
-        let cpi_accounts = Transfer {
+        // ✅ A code nobody registered is an empty account at the derived address
+        let referral_info = &ctx.accounts.referral_code;
+        require!(
+            referral_info.owner == ctx.program_id && !referral_info.data_is_empty(),
+            EscrowError::UnknownReferralCode
+        );
+        let referral = ReferralCode::try_deserialize(&mut &referral_info.data.borrow()[..])?;
+        msg!("Referral code {} resolves to {}", code, referral.referrer);
+
+        check_attestation(esc, contributor, ctx.accounts.attestation.as_ref())?;
+        check_gate(esc, contributor, ctx.accounts.gate_ata.as_ref())?;
+        let mint = esc.token_mint;
+        let needed = admit_contributor(esc, contributor, contributor, mint, amount, String::new())?;
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == contributor)
+            .ok_or(EscrowError::NotAContributor)?;
+        esc.referrers[i] = referral.referrer;
+
+        let cpi_accounts = TransferChecked {
             from: ctx.accounts.contributor_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
             to: ctx.accounts.vault_ata.to_account_info(),
             authority: ctx.accounts.contributor.to_account_info(),
         };
         let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        token_interface::transfer_checked(cpi_ctx, needed, ctx.accounts.mint.decimals)?;
+
+        emit!(DepositMade {
+            escrow: ctx.accounts.escrow.key(),
+            contributor,
+            amount: needed,
+            memo: String::new(),
+        });
+        Ok(())
+    }
+
+    /// ✅ Relayer-submitted deposit authorized by the token owner's off-chain signature
+    /// - The owner approves the vault authority as delegate once, then signs
+    ///   `escrow || amount (u64 LE) || nonce (u64 LE) || expires_at (i64 LE)`, `nonce`
+    ///   being their current `PermitNonce`; the relayer pays the fees
+    /// - Each permit is good for one deposit: the nonce moves on once it's used
+    /// - The signature is checked by an Ed25519 program instruction placed right before
+    ///   this one; `permit_sig` must be the signature it verified
+    pub fn deposit_with_permit(
+        ctx: Context<DepositWithPermit>,
+        name: String,
+        amount: u64,
+        expires_at: i64,
+        permit_sig: [u8; 64],
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let source = &ctx.accounts.contributor_ata;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(
+            source.owner != ctx.accounts.vault_auth.key(),
+            EscrowError::InvalidContributor
+        );
+        require!(
+            !esc.has_deposited(&source.owner),
+            EscrowError::AlreadyDeposited
+        );
+
+        require!(
+            Clock::get()?.unix_timestamp <= expires_at,
+            EscrowError::PermitExpired
+        );
+        let permit_nonce = &mut ctx.accounts.permit_nonce;
+        let mut message = esc.key().to_bytes().to_vec();
+        message.extend_from_slice(&amount.to_le_bytes());
+        message.extend_from_slice(&permit_nonce.nonce.to_le_bytes());
+        message.extend_from_slice(&expires_at.to_le_bytes());
+        verify_permit(
+            &ctx.accounts.instructions,
+            &source.owner,
+            &message,
+            &permit_sig,
+        )?;
+        permit_nonce.nonce += 1;
+
+        check_attestation(esc, source.owner, ctx.accounts.attestation.as_ref())?;
+        check_gate(esc, source.owner, ctx.accounts.gate_ata.as_ref())?;
+        let mint = esc.token_mint;
+        let needed =
+            admit_contributor(esc, source.owner, source.owner, mint, amount, String::new())?;
+        require!(
+            source.delegate == COption::Some(ctx.accounts.vault_auth.key())
+                && source.delegated_amount >= needed,
+            EscrowError::InsufficientDelegation
+        );
+
+        let cpi_accounts = TransferChecked {
+            from: source.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.vault_auth.to_account_info(),
+        };
+        let escrow_key = esc.key();
+
+        let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, needed, ctx.accounts.mint.decimals)
+    }
+
+    /// ✅ Sweep the entire vault to the fallback wallet once the escrow has been
+    /// inactive for `sweep_after_secs` without being distributed. Callable by anyone.
+    pub fn sweep_to_fallback(ctx: Context<SweepToFallback>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+
+        let fallback = esc
+            .fallback_sweep
+            .ok_or(EscrowError::NoFallbackConfigured)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= esc.last_activity_at.saturating_add(esc.sweep_after_secs),
+            EscrowError::SweepNotReady
+        );
+        require!(
+            ctx.accounts.fallback_ata.key()
+                == get_associated_token_address_with_program_id(
+                    &fallback,
+                    &esc.token_mint,
+                    &esc.token_program,
+                ),
+            EscrowError::InvalidRecipientAta
+        );
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.fallback_ata.to_account_info(),
+            authority: ctx.accounts.vault_auth.to_account_info(),
+        };
+        let escrow_key = esc.key();
 
-        esc.contributors.push(ctx.accounts.contributor.key());
+        let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token_interface::transfer_checked(
+            cpi_ctx,
+            ctx.accounts.vault_ata.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        esc.distributed = true;
+        esc.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Close the escrow (and its vault, if still open) and return the rent to the owner
+    /// - Allowed once distributed, or at any time while no one has deposited
+    /// - The vault must be empty, or already closed by its distribution
+    /// - Refused while held payouts or vesting grants are still owed
+    /// - With `reinit_cooldown_secs` set, pass `tombstone` to record the closure
+    pub fn close_escrow(ctx: Context<CloseEscrow>, name: String) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(
+            esc.distributed || esc.contributors.is_empty(),
+            EscrowError::PoolAlreadyStarted
+        );
+        // ✅ Shares still owed out of the vault would be lost with the escrow
+        require!(
+            esc.held_payouts.is_empty()
+                && esc
+                    .vesting_grants
+                    .iter()
+                    .all(|grant| grant.claimed >= grant.allocation),
+            EscrowError::UnclaimedPayouts
+        );
+        if esc.in_registry {
+            let registry = ctx
+                .accounts
+                .owner_registry
+                .as_mut()
+                .ok_or(EscrowError::OwnerRegistryMissing)?;
+            registry.escrow_count = registry.escrow_count.saturating_sub(1);
+        }
+        if esc.reinit_cooldown_secs > 0 {
+            let tombstone = ctx
+                .accounts
+                .tombstone
+                .as_mut()
+                .ok_or(EscrowError::MissingTombstone)?;
+            let now = Clock::get()?.unix_timestamp;
+            tombstone.escrow = esc.key();
+            tombstone.closed_at = now;
+            tombstone.reopens_at = now.saturating_add(esc.reinit_cooldown_secs);
+        }
+
+        // ✅ A vault already closed by its distribution is an empty account
+        let vault_info = ctx.accounts.vault_ata.to_account_info();
+        if !vault_info.data_is_empty() {
+            require!(
+                token::accessor::amount(&vault_info)? == 0,
+                EscrowError::VaultNotEmpty
+            );
+
+            let escrow_key = esc.key();
+            let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+            let signer: &[&[&[u8]]] = &[seeds];
+
+            let cpi_accounts = CloseAccount {
+                account: vault_info.clone(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: ctx.accounts.vault_auth.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            );
+            token_interface::close_account(cpi_ctx)?;
+        }
         Ok(())
     }
 
     /// ✅ Distribute tokens
     /// - Mode 0: Send all to `target_pubkey`
-    /// - Mode 1: Distribute equally to all except `target_pubkey`
+    /// - Mode 1: Distribute equally to all except `target_pubkey`; excluding the only
+    ///   contributor leaves no one to pay and is rejected
+    /// - Mode 12: `target_pubkey` wins `winner_bps` of the vault, the rest is split
+    ///   equally among the other contributors (dust goes to the winner)
+    /// - Mode 13: `target_pubkey` (the lead) gets `lead_amount`, the rest is split
+    ///   equally among the other contributors
+    /// - Mode 14: split weighted by time in the pool (`now - deposited_at`), dust goes
+    ///   to the earliest depositor; `target_pubkey` is ignored. Shares below the
+    ///   escrow's `min_payout` are raised to it out of the largest shares
+    /// - Mode 15: split equally among all contributors, dust goes to the earliest
+    ///   depositor; `target_pubkey` is ignored
+    /// - Mode 17: the last depositor gets `winner_bps` of the vault as a bonus, the rest
+    ///   is split equally among all contributors (dust goes to the last depositor);
+    ///   `target_pubkey` is ignored
+    ///
+    /// "Earliest depositor" means lowest `deposited_at`, ties going to the lowest pubkey,
+    /// so the dust recipient never depends on the order `contributors` is stored in.
+    /// Mode 1 and 13 leave their dust in the vault.
+    ///
+    /// Mode 16 (mode 15 paid out in another token) goes through `distribute_swapped`,
+    /// mode 18 (ranked payouts) through `distribute_ranked` and mode 19 (vesting) through
+    /// `distribute_vesting`.
+    ///
+    /// Mode 0 can also feed another escrow (pool of pools): pass that escrow as
+    /// `target_escrow`, its vault as `target_vault_ata` and its address as `target_pubkey`.
+    ///
+    /// With `opt_in_required`, recipients who haven't called `opt_in` are skipped and
+    /// their shares stay in the vault for `distribute_held`. With `dust_to_owner`, any
+    /// other remainder goes to `owner_ata`.
     pub fn distribute<'c: 'info, 'info>(
         ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
         name: String,
         mode: u8,
         target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
     ) -> Result<()> {
-        let esc = &mut ctx.accounts.escrow;
+        let esc = &ctx.accounts.escrow;
 
         require!(esc.name == name, EscrowError::NameMismatch);
-        require!(
-            esc.owner == ctx.accounts.owner.key(),
-            EscrowError::Unauthorized
-        );
-        require!(!esc.distributed, EscrowError::AlreadyDistributed);
-        require!(esc.contributors.len() == 5, EscrowError::NotFull);
+        check_distributable(esc, mode)?;
 
-        let vault_balance = ctx.accounts.vault_ata.amount;
-        require!(vault_balance > 0, EscrowError::InvalidMode);
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        assert_accounting_matches(esc, ctx.accounts.vault_ata.amount, mode)?;
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let mut held = vec![];
+        let recipient_count = match (&ctx.accounts.target_escrow, &ctx.accounts.target_vault_ata) {
+            (None, None) => {
+                let mut payouts = distribution_plan(
+                    esc,
+                    vault_balance,
+                    mode,
+                    target_pubkey,
+                    winner_bps,
+                    lead_amount,
+                )?;
+                check_committed_plan(esc, mode, target_pubkey, winner_bps, lead_amount, &payouts)?;
+                held = hold_back_unopted(esc, &mut payouts)?;
+                pay_out(
+                    ctx.remaining_accounts,
+                    &ctx.accounts.vault_ata,
+                    &ctx.accounts.vault_auth,
+                    &ctx.accounts.token_program,
+                    esc.key(),
+                    ctx.bumps.vault_auth,
+                    &ctx.accounts.mint,
+                    &payouts,
+                )?;
+                payouts.len() as u8
+            }
 
-        match mode {
-            // ✅ Mode 0: Send all funds to one contributor
-            0 => {
+            // ✅ Mode 0 into another escrow's vault
+            (Some(target_escrow), Some(target_vault)) if mode == 0 => {
+                // ✅ An escrow can't opt in
+                require!(!esc.opt_in_required, EscrowError::NoOptedInRecipients);
+                check_unique_payers(esc)?;
+                check_target_whitelisted(esc, target_pubkey)?;
+                check_committed_plan(
+                    esc,
+                    mode,
+                    target_pubkey,
+                    winner_bps,
+                    lead_amount,
+                    &[(target_pubkey, vault_balance)],
+                )?;
+                let (target_vault_auth, _) = Pubkey::find_program_address(
+                    &[b"vault-auth", target_escrow.key().as_ref()],
+                    ctx.program_id,
+                );
                 require!(
-                    esc.contributors.contains(&target_pubkey),
-                    EscrowError::InvalidTarget
+                    target_vault.key() != ctx.accounts.vault_ata.key(),
+                    EscrowError::SelfTransfer
+                );
+                require!(
+                    target_escrow.key() == target_pubkey
+                        && target_escrow.key() != esc.key()
+                        && target_escrow.is_supported_version()
+                        && target_escrow.token_mint == esc.token_mint
+                        && !target_escrow.distributed
+                        && !target_escrow.frozen
+                        && target_vault.key()
+                            == get_associated_token_address_with_program_id(
+                                &target_vault_auth,
+                                &esc.token_mint,
+                                &esc.token_program,
+                            ),
+                    EscrowError::InvalidTargetEscrow
                 );
 
-                let target = target_pubkey;
-                let recipient_ata = get_associated_token_address(&target, &esc.token_mint);
-
-                // ✅ Find matching AccountInfo passed in ctx.remaining_accounts
-                let ata_info = ctx
-                    .remaining_accounts
-                    .iter()
-                    .find(|acc| acc.key() == recipient_ata)
-                    .ok_or(EscrowError::MissingRecipientAta)?
-                    .clone();
-                let cpi_accounts = Transfer {
+                let cpi_accounts = TransferChecked {
                     from: ctx.accounts.vault_ata.to_account_info(),
-                    to: ata_info,
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: target_vault.to_account_info(),
                     authority: ctx.accounts.vault_auth.to_account_info(),
                 };
                 let escrow_key = esc.key();
@@ -106,142 +994,4459 @@ pub mod bonk_escrow_final {
                     cpi_accounts,
                     signer,
                 );
-
-                token::transfer(cpi_ctx, vault_balance)?;
+                token_interface::transfer_checked(
+                    cpi_ctx,
+                    vault_balance,
+                    ctx.accounts.mint.decimals,
+                )?;
+                1
             }
 
-            // ✅ Mode 1: Distribute equally to all except excluded contributor
-            1 => {
-                let recipients: Vec<Pubkey> = esc
-                    .contributors
-                    .iter()
-                    .cloned()
-                    .filter(|c| *c != target_pubkey)
-                    .collect();
-
-                require!(recipients.len() > 0, EscrowError::InvalidMode);
-                let share = vault_balance / recipients.len() as u64;
-
-                for recipient in recipients {
-                    let recipient_ata = get_associated_token_address(&recipient, &esc.token_mint);
-
-                    // ✅ Find matching AccountInfo passed in ctx.remaining_accounts
-                    let ata_info = ctx
-                        .remaining_accounts
-                        .iter()
-                        .find(|acc| acc.key() == recipient_ata)
-                        .ok_or(EscrowError::MissingRecipientAta)?
-                        .clone();
-
-                    let cpi_accounts = Transfer {
-                        from: ctx.accounts.vault_ata.to_account_info(),
-                        to: ata_info,
-                        authority: ctx.accounts.vault_auth.to_account_info(),
-                    };
-                    let escrow_key = esc.key();
-
-                    let seeds: &[&[u8]] =
-                        &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
-                    let signer: &[&[&[u8]]] = &[seeds];
-
-                    let cpi_ctx = CpiContext::new_with_signer(
-                        ctx.accounts.token_program.to_account_info(),
-                        cpi_accounts,
-                        signer,
-                    );
-
-                    token::transfer(cpi_ctx, share)?;
-                }
-            }
+            _ => return Err(error!(EscrowError::InvalidTargetEscrow)),
+        };
 
-            _ => return Err(error!(EscrowError::InvalidMode)),
-        }
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            mode,
+            vault_balance,
+            recipient_count,
+        )?;
+        let held_total = held.iter().map(|p| p.amount).sum();
+        sweep_dust_to_owner(ctx.accounts, ctx.bumps.vault_auth, held_total)?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
 
-        esc.distributed = true;
+        ctx.accounts.escrow.held_payouts = held;
+        ctx.accounts.escrow.record_mode(mode);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = owner,
-        seeds = [b"escrow", owner.key().as_ref(), name.as_bytes()],
-        bump,
-        space = 8 + 32 + 32 + 4 + (5 * 32) + 1 + 4 + 32
-    )]
-    pub escrow: Account<'info, EscrowState>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    pub mint: Account<'info, Mint>,
+    /// ✅ Pay the shares `distribute` held back to recipients who have opted in since;
+    /// the rest stay held for a later pass
+    pub fn distribute_held<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
 
-    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
-    /// CHECK: PDA authority
-    pub vault_auth: AccountInfo<'info>,
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(esc.distributed, EscrowError::NotDistributed);
 
-    #[account(
-        init,
-        payer = owner,
-        associated_token::mint = mint,
-        associated_token::authority = vault_auth
-    )]
-    pub vault_ata: Account<'info, TokenAccount>,
+        let mut payouts = esc
+            .held_payouts
+            .iter()
+            .map(|p| (p.recipient, p.amount))
+            .collect();
+        let held = hold_back_unopted(esc, &mut payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-}
+        ctx.accounts.escrow.held_payouts = held;
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct Deposit<'info> {
-    #[account(
-        mut,
-        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
-        bump
-    )]
-    pub escrow: Account<'info, EscrowState>,
-    #[account(mut)]
-    pub contributor: Signer<'info>,
-    #[account(mut, associated_token::mint = escrow.token_mint, associated_token::authority = contributor)]
-    pub contributor_ata: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_ata: Account<'info, TokenAccount>,
-    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
-    /// CHECK: PDA authority
-    pub vault_auth: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-}
+    /// ✅ Owner escape hatch when some recipients can never receive: split the vault
+    /// equally among everyone not in `skip` (recorded on the escrow for transparency)
+    pub fn force_distribute_skipping<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        skip: Vec<Pubkey>,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+        let mut skip = skip;
+        skip.sort();
+        skip.dedup();
 
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct Distribute<'info> {
-    #[account(
-        mut,
-        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
-        bump
-    )]
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, 1)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        check_unique_payers(esc)?;
+        require!(
+            skip.iter().all(|s| esc.contributors.contains(s)),
+            EscrowError::NotAContributor
+        );
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let recipients: Vec<Pubkey> = esc
+            .contributors
+            .iter()
+            .cloned()
+            .filter(|c| !skip.contains(c))
+            .collect();
+        require!(!recipients.is_empty(), EscrowError::CannotSkipAll);
+        require!(
+            !esc.is_nft || recipients.len() == 1,
+            EscrowError::CannotSplitNft
+        );
+        require!(
+            vault_balance >= recipients.len() as u64,
+            EscrowError::BalanceTooLowToSplit
+        );
+
+        let share = vault_balance / recipients.len() as u64;
+        let payouts: Vec<(Pubkey, u64)> = recipients.into_iter().map(|r| (r, share)).collect();
+        check_committed_plan(esc, 1, Pubkey::default(), 0, 0, &payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            1,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let esc = &mut ctx.accounts.escrow;
+        esc.skipped = skip;
+        esc.record_mode(1);
+        esc.distributed = true;
+        esc.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Commit to `keccak(seed)` before anyone deposits; the seed later picks the winner
+    /// - Locked once the first deposit lands (`CommitmentLocked`), so the owner can't
+    ///   swap in a seed that favours a known contributor set
+    pub fn commit_winner(ctx: Context<OwnerUpdate>, name: String, hash: [u8; 32]) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(!esc.is_full(), EscrowError::PoolAlreadyFull);
+        require!(esc.contributors.is_empty(), EscrowError::CommitmentLocked);
+
+        esc.winner_commitment = Some(hash);
+        Ok(())
+    }
+
+    /// ✅ Reveal the committed seed and send the whole vault to the contributor it picks
+    /// - Winner index: `u64::from_le_bytes(keccak(seed || slot_hash)[..8]) % contributors.len()`,
+    ///   where `slot_hash` is the newest entry of the `SlotHashes` sysvar at reveal time,
+    ///   so the owner's seed alone doesn't decide the outcome
+    /// - `slot_hash` is returned alongside the winner for anyone re-checking the draw
+    pub fn reveal_and_distribute<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        seed: [u8; 32],
+    ) -> Result<LotteryWinner> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, 0)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+
+        let commitment = esc.winner_commitment.ok_or(EscrowError::NotCommitted)?;
+        require!(
+            keccak::hash(&seed).to_bytes() == commitment,
+            EscrowError::CommitMismatch
+        );
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let slot_hash = latest_slot_hash(&ctx.accounts.slot_hashes)?;
+        let draw = keccak::hashv(&[&seed, &slot_hash]).to_bytes();
+        let mut index_bytes = [0u8; 8];
+        index_bytes.copy_from_slice(&draw[..8]);
+        let index = u64::from_le_bytes(index_bytes) % esc.contributors.len() as u64;
+        let winner = esc.contributors[index as usize];
+        msg!("Commit-reveal winner: {} (index {})", winner, index);
+
+        // ✅ Surface the winner before any transfer can fail. CPIs reset return data,
+        // so the returned value below sets it again once the payout succeeds.
+        let result = LotteryWinner {
+            winner,
+            index: index as u8,
+            slot_hash,
+        };
+        anchor_lang::solana_program::program::set_return_data(&result.try_to_vec()?);
+
+        let payouts = distribution_plan(esc, vault_balance, 0, winner, 0, 0)?;
+        check_committed_plan(esc, 0, winner, 0, 0, &payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            0,
+            vault_balance,
+            1,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        ctx.accounts.escrow.record_mode(0);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(result)
+    }
+
+    /// ✅ Record the contributor `distribute_stored` will target (owner only, re-callable)
+    pub fn set_target(ctx: Context<OwnerUpdate>, name: String, target: Pubkey) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(
+            esc.contributors.contains(&target),
+            EscrowError::NotAContributor
+        );
+
+        esc.stored_target = Some(target);
+        msg!("Distribution target set to {}", target);
+        Ok(())
+    }
+
+    /// ✅ `distribute` against the target recorded by `set_target`
+    pub fn distribute_stored<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        mode: u8,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, mode)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        let target = esc.stored_target.ok_or(EscrowError::NoStoredTarget)?;
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        assert_accounting_matches(esc, ctx.accounts.vault_ata.amount, mode)?;
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let payouts = distribution_plan(esc, vault_balance, mode, target, winner_bps, lead_amount)?;
+        check_committed_plan(esc, mode, target, winner_bps, lead_amount, &payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            mode,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        ctx.accounts.escrow.record_mode(mode);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Liveness fallback: once a full pool has waited `distribute_fallback_secs` past
+    /// its last deposit, any contributor may split it equally among all contributors
+    /// - The owner's mode and target are ignored; `burn_bps` still applies
+    /// - The vault stays open and no audit record is written, both being the owner's to pay for
+    pub fn fallback_distribute<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, FallbackDistribute<'info>>,
+        name: String,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(esc.is_full(), EscrowError::NotFull);
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        require!(
+            esc.has_deposited(ctx.accounts.contributor.key),
+            EscrowError::NotAContributor
+        );
+
+        // ✅ The pool filled with its latest deposit
+        let filled_at = esc.deposited_at.iter().copied().max().unwrap_or_default();
+        require!(
+            esc.distribute_fallback_secs > 0
+                && Clock::get()?.unix_timestamp
+                    >= filled_at.saturating_add(esc.distribute_fallback_secs),
+            EscrowError::FallbackNotReady
+        );
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let burned = burn_from_vault(
+            esc,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            ctx.bumps.vault_auth,
+        )?;
+        let vault_balance = ctx.accounts.vault_ata.amount - burned;
+
+        // ✅ No `committed_plan` check: this is the contributors' way out when the owner
+        // never distributes, so the owner's commitment can't be allowed to block it
+        let payouts = distribution_plan(esc, vault_balance, 15, Pubkey::default(), 0, 0)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        ctx.accounts.escrow.record_mode(15);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ `distribute` for wSOL pools, paying recipients in native SOL
+    /// - `remaining_accounts`: the temporary unwrap account (PDA `[b"unwrap", escrow]`)
+    ///   first, then the recipient wallets (writable) instead of their ATAs
+    /// - The owner fronts the temporary account's rent and gets it back in the same call
+    pub fn distribute_native<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, mode)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        require!(
+            esc.token_mint == native_mint::ID,
+            EscrowError::NotNativeMint
+        );
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let payouts = distribution_plan(
+            esc,
+            vault_balance,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        check_committed_plan(esc, mode, target_pubkey, winner_bps, lead_amount, &payouts)?;
+        pay_out_native(
+            ctx.accounts,
+            ctx.remaining_accounts,
+            ctx.bumps.vault_auth,
+            ctx.program_id,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            mode,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        ctx.accounts.escrow.record_mode(mode);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Mode 16: equal split like mode 15, with each share swapped into `output_mint`
+    /// through the owner-chosen `swap_program` before it reaches the recipient
+    /// - `remaining_accounts`: every recipient's `output_mint` ATA first (one per
+    ///   contributor), then the pool accounts the swap program needs, in its order
+    /// - Each recipient must receive at least `min_amount_out`, else `SlippageExceeded`
+    ///
+    /// Swap CPI boundary: an Anchor-style `swap(amount_in: u64, minimum_amount_out: u64)`
+    /// taking `[source, destination, authority (signer), token_program, ..pool accounts]`.
+    /// Each share is first moved to `swap_source`, a scratch account owned by the
+    /// `swap_auth` PDA, and the swap is signed by `swap_auth` alone: the swap program
+    /// never holds the vault authority's signature. It must consume the whole share.
+    pub fn distribute_swapped<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, DistributeSwapped<'info>>,
+        name: String,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, 16)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let burned = burn_from_vault(
+            esc,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            ctx.bumps.vault_auth,
+        )?;
+        let vault_balance = ctx.accounts.vault_ata.amount - burned;
+
+        let payouts = distribution_plan(esc, vault_balance, 15, Pubkey::default(), 0, 0)?;
+        check_committed_plan(esc, 16, Pubkey::default(), 0, 0, &payouts)?;
+        pay_out_swapped(
+            ctx.accounts,
+            ctx.remaining_accounts,
+            ctx.bumps.vault_auth,
+            ctx.bumps.swap_auth,
+            min_amount_out,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            16,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+
+        ctx.accounts.escrow.record_mode(16);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Mode 18: pay a ranked pool, `ranking[i]` receiving `rank_bps[i]` of the vault
+    /// - `ranking` must list every contributor exactly once, best first, and `rank_bps`
+    ///   must give one share per rank adding up to 10000
+    /// - Dust goes to first place
+    pub fn distribute_ranked<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        ranking: Vec<Pubkey>,
+        rank_bps: Vec<u16>,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, 18)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let payouts = ranked_plan(esc, vault_balance, &ranking, &rank_bps)?;
+        check_committed_plan(esc, 18, Pubkey::default(), 0, 0, &payouts)?;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            18,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+        close_vault_if_empty(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        ctx.accounts.escrow.record_mode(18);
+        ctx.accounts.escrow.distributed = true;
+        ctx.accounts.escrow.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Mode 19: allocate the vault like mode 15 but vest each share linearly instead of
+    /// paying it out; recipients pull what has vested with `claim_vested`
+    /// - Nothing vests before `start + cliff_secs`; everything has by `start + duration_secs`
+    /// - The escrow counts as distributed at once, the tokens stay in the vault
+    pub fn distribute_vesting(
+        ctx: Context<Distribute>,
+        name: String,
+        start: i64,
+        cliff_secs: i64,
+        duration_secs: i64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, 19)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        require!(
+            duration_secs > 0 && (0..=duration_secs).contains(&cliff_secs),
+            EscrowError::InvalidVestingSchedule
+        );
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let payouts = distribution_plan(esc, vault_balance, 15, Pubkey::default(), 0, 0)?;
+        check_committed_plan(esc, 19, Pubkey::default(), 0, 0, &payouts)?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            19,
+            vault_balance,
+            payouts.len() as u8,
+        )?;
+
+        let esc = &mut ctx.accounts.escrow;
+        esc.vesting = Some(VestingSchedule {
+            start,
+            cliff_secs,
+            duration_secs,
+        });
+        esc.vesting_grants = payouts
+            .into_iter()
+            .map(|(recipient, allocation)| VestingGrant {
+                recipient,
+                allocation,
+                claimed: 0,
+            })
+            .collect();
+        esc.record_mode(19);
+        esc.distributed = true;
+        esc.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Pull the part of the caller's mode 19 allocation that has vested so far
+    pub fn claim_vested(ctx: Context<ClaimVested>, name: String) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+        let recipient = ctx.accounts.recipient.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        let schedule = esc.vesting.clone().ok_or(EscrowError::NothingVested)?;
+        let i = esc
+            .vesting_grants
+            .iter()
+            .position(|g| g.recipient == recipient)
+            .ok_or(EscrowError::NotAContributor)?;
+
+        let grant = &esc.vesting_grants[i];
+        let now = Clock::get()?.unix_timestamp;
+        let claimable = schedule
+            .vested(grant.allocation, now)
+            .saturating_sub(grant.claimed);
+        require!(claimable > 0, EscrowError::NothingVested);
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.recipient_ata.to_account_info(),
+            authority: ctx.accounts.vault_auth.to_account_info(),
+        };
+        let escrow_key = esc.key();
+
+        let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, claimable, ctx.accounts.mint.decimals)?;
+
+        ctx.accounts.escrow.vesting_grants[i].claimed += claimable;
+        Ok(())
+    }
+
+    /// ✅ Commit to the exact payouts a distribution will make (owner only, once)
+    /// - Hashes the `(recipient, amount)` list the vault would be split into right now,
+    ///   with the same parameters `distribute` takes
+    /// - Every distribution path except `fallback_distribute` must then reproduce it,
+    ///   else `PlanHashMismatch`; a vault balance that moves breaks the match too
+    pub fn commit_plan(
+        ctx: Context<CommitPlan>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(esc.is_full(), EscrowError::NotFull);
+        require!(
+            esc.committed_plan.is_none(),
+            EscrowError::PlanAlreadyCommitted
+        );
+
+        let vault = ctx.accounts.vault_ata.amount;
+        let burned = (vault as u128 * esc.burn_bps as u128 / 10_000) as u64;
+        let payouts = distribution_plan(
+            esc,
+            vault - burned,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        let hash = plan_hash(mode, target_pubkey, winner_bps, lead_amount, &payouts);
+        let now = Clock::get()?.unix_timestamp;
+
+        let esc = &mut ctx.accounts.escrow;
+        esc.committed_plan = Some(hash);
+        esc.committed_rule = Some(plan_hash(mode, target_pubkey, winner_bps, lead_amount, &[]));
+        esc.plan_committed_at = now;
+
+        emit!(PlanCommitted {
+            escrow: esc.key(),
+            hash,
+            committed_at: now,
+        });
+        Ok(())
+    }
+
+    /// ✅ Commit to a distribution paid out over several `distribute_batch` calls
+    /// - Burns the `burn_bps` cut and snapshots the vault; the plan for these parameters
+    ///   is hashed and every batch must reproduce it, so they can't change midway
+    /// - Until the last batch, anything else that would touch the pool is refused
+    /// - Mode 14 is time-weighted, so its plan can't be reproduced later and isn't batchable
+    pub fn begin_distribution<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Distribute<'info>>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        check_distributable(esc, mode)?;
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        require!(mode != 14, EscrowError::InvalidMode);
+
+        require!(ctx.accounts.vault_ata.amount > 0, EscrowError::InvalidMode);
+        let vault_balance =
+            ctx.accounts.vault_ata.amount - burn_cut(ctx.accounts, ctx.bumps.vault_auth)?;
+
+        let payouts = distribution_plan(
+            esc,
+            vault_balance,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        check_committed_plan(esc, mode, target_pubkey, winner_bps, lead_amount, &payouts)?;
+        let hash = plan_hash(mode, target_pubkey, winner_bps, lead_amount, &payouts);
+
+        let esc = &mut ctx.accounts.escrow;
+        esc.pending_plan = Some(hash);
+        esc.plan_total = vault_balance;
+        esc.plan_cursor = 0;
+        Ok(())
+    }
+
+    /// ✅ Pay the next `count` recipients of the plan committed by `begin_distribution`
+    /// - A plan pays at most the 5 slots, which a single `distribute` already fits in
+    ///   compute; batching lets a client spread those transfers over several
+    ///   transactions (e.g. next to the instructions creating recipients' ATAs) while
+    ///   the commitment keeps every batch paying the same plan
+    /// - `count` must reach at least one unpaid recipient, else `EmptyBatch`
+    /// - The parameters must be the committed ones, else `PlanMismatch`
+    /// - Anyone may submit a batch: the plan is fixed, so an owner who walks away
+    ///   halfway can't strand the rest
+    /// - The last batch finishes the distribution as `distribute` would
+    pub fn distribute_batch<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, DistributeBatch<'info>>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+        count: u8,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        let committed = esc.pending_plan.ok_or(EscrowError::NoPendingPlan)?;
+
+        let payouts = distribution_plan(
+            esc,
+            esc.plan_total,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        require!(
+            plan_hash(mode, target_pubkey, winner_bps, lead_amount, &payouts) == committed,
+            EscrowError::PlanMismatch
+        );
+
+        let start = esc.plan_cursor as usize;
+        let end = (start + count as usize).min(payouts.len());
+        require!(end > start, EscrowError::EmptyBatch);
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint,
+            &payouts[start..end],
+        )?;
+
+        let plan_total = esc.plan_total;
+        let esc = &mut ctx.accounts.escrow;
+        esc.plan_cursor = end as u8;
+        if end < payouts.len() {
+            return Ok(());
+        }
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            mode,
+            plan_total,
+            payouts.len() as u8,
+        )?;
+        close_vault(
+            &ctx.accounts.escrow,
+            &ctx.accounts.vault_ata,
+            &ctx.accounts.owner,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            ctx.bumps.vault_auth,
+        )?;
+
+        let esc = &mut ctx.accounts.escrow;
+        esc.pending_plan = None;
+        esc.record_mode(mode);
+        esc.distributed = true;
+        esc.distributed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// ✅ Attach a second mint and vault for two-token pools (owner only)
+    pub fn init_vault_b(ctx: Context<InitVaultB>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(
+            esc.token_mint_b.is_none(),
+            EscrowError::SecondMintAlreadySet
+        );
+
+        esc.token_mint_b = Some(ctx.accounts.mint_b.key());
+        Ok(())
+    }
+
+    /// ✅ Deposit any positive amount of the second mint into vault B
+    /// - Only existing contributors may top up vault B
+    pub fn deposit_b(ctx: Context<DepositB>, name: String, amount: u64) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(
+            esc.token_mint_b == Some(ctx.accounts.mint_b.key()),
+            EscrowError::UnknownMint
+        );
+        require!(!esc.distributed_b, EscrowError::AlreadyDistributed);
+        require!(
+            esc.has_deposited(ctx.accounts.contributor.key),
+            EscrowError::NotAContributor
+        );
+        require!(amount > 0, EscrowError::InvalidDepositAmount);
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.contributor_ata_b.to_account_info(),
+            mint: ctx.accounts.mint_b.to_account_info(),
+            to: ctx.accounts.vault_ata_b.to_account_info(),
+            authority: ctx.accounts.contributor.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint_b.decimals)
+    }
+
+    /// ✅ Distribute vault B using the same modes as `distribute`
+    /// - After `commit_plan`, only with the committed parameters; vault B's own balance
+    ///   sets the amounts
+    /// - Not recorded in `mode_history`, which tracks the main vault
+    pub fn distribute_b<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, DistributeB<'info>>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<()> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(
+            esc.token_mint_b == Some(ctx.accounts.mint_b.key()),
+            EscrowError::UnknownMint
+        );
+        require!(!esc.distributed_b, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(esc.is_full(), EscrowError::NotFull);
+        require!(!esc.opt_in_required, EscrowError::OptInUnsupported);
+        require!(esc.mode_allowed(mode), EscrowError::ModeNotAllowed);
+        if let Some(rule) = esc.committed_rule {
+            require!(
+                plan_hash(mode, target_pubkey, winner_bps, lead_amount, &[]) == rule,
+                EscrowError::PlanHashMismatch
+            );
+        }
+
+        let vault_balance = ctx.accounts.vault_ata_b.amount;
+        require!(vault_balance > 0, EscrowError::InvalidMode);
+
+        let payouts = distribution_plan(
+            esc,
+            vault_balance,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        let recipient_count = payouts.len() as u8;
+        pay_out(
+            ctx.remaining_accounts,
+            &ctx.accounts.vault_ata_b,
+            &ctx.accounts.vault_auth,
+            &ctx.accounts.token_program,
+            esc.key(),
+            ctx.bumps.vault_auth,
+            &ctx.accounts.mint_b,
+            &payouts,
+        )?;
+
+        record_audit(
+            &mut ctx.accounts.audit_log,
+            ctx.accounts.escrow.key(),
+            mode,
+            vault_balance,
+            recipient_count,
+        )?;
+
+        ctx.accounts.escrow.distributed_b = true;
+        Ok(())
+    }
+
+    /// ✅ Read the pooled balance and mint straight from the escrow's own vault
+    pub fn vault_info(ctx: Context<VaultInfo>, name: String) -> Result<VaultSnapshot> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        Ok(VaultSnapshot {
+            amount: ctx.accounts.vault_ata.amount,
+            mint: esc.token_mint,
+        })
+    }
+
+    /// ✅ Pre-register contributor slots (owner only); each slot is funded by that
+    /// contributor's own later deposit
+    pub fn import_contributors(
+        ctx: Context<OwnerUpdate>,
+        name: String,
+        keys: Vec<Pubkey>,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(
+            esc.contributors.len() + keys.len() <= 5,
+            EscrowError::MaxContributorsReached
+        );
+
+        for key in keys {
+            require!(
+                !esc.contributors.contains(&key),
+                EscrowError::DuplicateContributor
+            );
+            require!(
+                !(esc.independent_owner && esc.owner == key),
+                EscrowError::OwnerCannotDeposit
+            );
+            esc.contributors.push(key);
+            esc.amounts.push(0);
+            esc.refunded.push(false);
+            esc.deposited_at.push(0);
+            esc.memos.push(String::new());
+            let token_mint = esc.token_mint;
+            esc.deposit_mints.push(token_mint);
+            esc.referrers.push(Pubkey::default());
+            esc.payers.push(Pubkey::default());
+        }
+        Ok(())
+    }
+
+    /// ✅ Drop a contributor slot that hasn't been funded yet (owner only)
+    pub fn remove_contributor(ctx: Context<OwnerUpdate>, name: String, who: Pubkey) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == who)
+            .ok_or(EscrowError::NotAContributor)?;
+        require!(esc.amounts[i] == 0, EscrowError::ContributorHasFunds);
+
+        esc.contributors.remove(i);
+        esc.amounts.remove(i);
+        esc.refunded.remove(i);
+        esc.deposited_at.remove(i);
+        esc.memos.remove(i);
+        esc.deposit_mints.remove(i);
+        esc.referrers.remove(i);
+        esc.payers.remove(i);
+        esc.opted_in.retain(|c| *c != who);
+        Ok(())
+    }
+
+    /// ✅ Pad per-slot vectors that fell behind `contributors` (owner only)
+    /// - Missing entries get defaults: 0 for the deposit time, the escrow mint, the
+    ///   contributor as payer, no memo or referrer
+    /// - A slot whose amount was lost is recorded as unfunded: amount 0 and marked
+    ///   refunded, so nothing is paid back or counted for a deposit the books can't show
+    /// - Only for `REPAIRABLE_VERSION` escrows, which it upgrades to `ESCROW_VERSION`; a
+    ///   vector longer than `contributors` can't be attributed to anyone and stays
+    ///   `StateCorrupted`
+    pub fn repair_vectors(ctx: Context<OwnerUpdate>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(
+            esc.version == REPAIRABLE_VERSION,
+            EscrowError::UnsupportedVersion
+        );
+
+        let n = esc.contributors.len();
+        let token_mint = esc.token_mint;
+        let padded = [
+            esc.amounts.len(),
+            esc.refunded.len(),
+            esc.deposited_at.len(),
+            esc.memos.len(),
+            esc.deposit_mints.len(),
+            esc.referrers.len(),
+            esc.payers.len(),
+        ]
+        .iter()
+        .map(|len| n.saturating_sub(*len))
+        .sum::<usize>();
+
+        let funded = esc.amounts.len();
+        while esc.amounts.len() < n {
+            esc.amounts.push(0);
+        }
+        while esc.refunded.len() < n {
+            esc.refunded.push(false);
+        }
+        for refunded in esc.refunded.iter_mut().take(n).skip(funded) {
+            *refunded = true;
+        }
+        while esc.deposited_at.len() < n {
+            esc.deposited_at.push(0);
+        }
+        while esc.memos.len() < n {
+            esc.memos.push(String::new());
+        }
+        while esc.deposit_mints.len() < n {
+            esc.deposit_mints.push(token_mint);
+        }
+        while esc.referrers.len() < n {
+            esc.referrers.push(Pubkey::default());
+        }
+        while esc.payers.len() < n {
+            let contributor = esc.contributors[esc.payers.len()];
+            esc.payers.push(contributor);
+        }
+        assert_vectors_aligned(esc)?;
+        esc.version = ESCROW_VERSION;
+
+        msg!("Padded {} missing per-slot entries", padded);
+        Ok(())
+    }
+
+    /// ✅ Emergency stop (owner only): every instruction that changes the escrow or moves
+    /// its tokens fails with `EscrowFrozen` until `unfreeze_all`; views keep working
+    pub fn freeze_all(ctx: Context<FreezeUpdate>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        esc.frozen = true;
+        msg!("Escrow {} frozen", name);
+        Ok(())
+    }
+
+    /// ✅ Lift a `freeze_all` (owner only)
+    pub fn unfreeze_all(ctx: Context<FreezeUpdate>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        esc.frozen = false;
+        msg!("Escrow {} unfrozen", name);
+        Ok(())
+    }
+
+    /// ✅ Return a contributor's deposit before distribution
+    /// - The slot stays taken but unfunded; the owner can `remove_contributor` it
+    /// - A slot can only be refunded once and can't be refilled by the same contributor
+    /// - Paid to `refund_destination` when given (any account of the escrow mint),
+    ///   otherwise back to `contributor_ata`
+    /// - If the vault holds less than the recorded deposits, the refund fails with
+    ///   `VaultShortfall` unless `accept_haircut` takes the pro-rata share instead
+    /// - Paid in the mint the slot was funded with, from that mint's vault
+    pub fn refund(ctx: Context<Refund>, name: String, accept_haircut: bool) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let contributor = ctx.accounts.contributor.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == contributor)
+            .ok_or(EscrowError::NotAContributor)?;
+        require!(!esc.refunded[i], EscrowError::AlreadyRefunded);
+        require!(esc.amounts[i] > 0, EscrowError::InvalidDepositAmount);
+        require!(
+            esc.deposit_mints[i] == ctx.accounts.mint.key(),
+            EscrowError::UnknownMint
+        );
+        let amount = refund_share(
+            esc,
+            esc.deposit_mints[i],
+            ctx.accounts.vault_ata.amount,
+            esc.amounts[i],
+            accept_haircut,
+        )?;
+
+        // ✅ Mark the slot before the CPI so it can't be refunded twice
+        esc.amounts[i] = 0;
+        esc.refunded[i] = true;
+        esc.last_activity_at = Clock::get()?.unix_timestamp;
+
+        let destination = ctx
+            .accounts
+            .refund_destination
+            .as_ref()
+            .unwrap_or(&ctx.accounts.contributor_ata);
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: destination.to_account_info(),
+            authority: ctx.accounts.vault_auth.to_account_info(),
+        };
+        let escrow_key = esc.key();
+
+        let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+
+        msg!("Refunded {} to {}", amount, destination.key());
+        Ok(())
+    }
+
+    /// ✅ Owner removes `who` from the pool before distribution, refunding their deposit
+    pub fn eject_contributor(
+        ctx: Context<EjectContributor>,
+        name: String,
+        who: Pubkey,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        assert_vectors_aligned(esc)?;
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == who)
+            .ok_or(EscrowError::NotAContributor)?;
+        require!(
+            esc.deposit_mints[i] == ctx.accounts.mint.key(),
+            EscrowError::UnknownMint
+        );
+        // ✅ A forced exit can't wait for consent: a short vault pays the pro-rata share
+        let amount = refund_share(
+            esc,
+            esc.deposit_mints[i],
+            ctx.accounts.vault_ata.amount,
+            esc.amounts[i],
+            true,
+        )?;
+
+        esc.contributors.remove(i);
+        esc.amounts.remove(i);
+        esc.refunded.remove(i);
+        esc.deposited_at.remove(i);
+        esc.memos.remove(i);
+        esc.deposit_mints.remove(i);
+        esc.referrers.remove(i);
+        esc.payers.remove(i);
+        esc.opted_in.retain(|c| *c != who);
+        esc.last_activity_at = Clock::get()?.unix_timestamp;
+
+        if amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.vault_ata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.contributor_ata.to_account_info(),
+                authority: ctx.accounts.vault_auth.to_account_info(),
+            };
+            let escrow_key = esc.key();
+
+            let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+            let signer: &[&[&[u8]]] = &[seeds];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+        }
+
+        emit!(Ejected {
+            escrow: ctx.accounts.escrow.key(),
+            contributor: who,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// ✅ Change the per-slot deposit before anyone has joined (owner only)
+    pub fn set_required_deposit(
+        ctx: Context<OwnerUpdate>,
+        name: String,
+        amount: u64,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(esc.contributors.is_empty(), EscrowError::PoolAlreadyStarted);
+        require!(amount > 0, EscrowError::InvalidDepositAmount);
+        require!(!esc.is_nft || amount == 1, EscrowError::CannotSplitNft);
+
+        esc.required_deposit = amount;
+        Ok(())
+    }
+
+    /// ✅ Push the deposit deadline back so a nearly full pool can still fill (owner only)
+    /// - Only before distribution, and only to a later time than the current deadline
+    pub fn extend_deadline(
+        ctx: Context<OwnerUpdate>,
+        name: String,
+        new_deadline: i64,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(
+            esc.deposit_deadline != 0 && new_deadline > esc.deposit_deadline,
+            EscrowError::DeadlineNotExtended
+        );
+
+        let old_deadline = esc.deposit_deadline;
+        esc.deposit_deadline = new_deadline;
+        emit!(DeadlineExtended {
+            escrow: esc.key(),
+            old_deadline,
+            new_deadline,
+        });
+        Ok(())
+    }
+
+    /// ✅ Replace the escrow's category tags (owner only, at any time)
+    pub fn set_tags(ctx: Context<OwnerUpdate>, name: String, tags: Vec<u32>) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(tags.len() <= MAX_TAGS, EscrowError::TooManyTags);
+
+        esc.tags = tags.clone();
+        emit!(TagsSet {
+            escrow: esc.key(),
+            tags,
+        });
+        Ok(())
+    }
+
+    /// ✅ Owner adds bonus tokens to the vault (e.g. matching) without taking a slot
+    pub fn owner_topup(ctx: Context<OwnerTopup>, name: String, amount: u64) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        require!(amount > 0, EscrowError::InvalidDepositAmount);
+
+        esc.bonus = esc
+            .bonus
+            .checked_add(amount)
+            .ok_or(EscrowError::DistributionMathError)?;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.owner_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)
+    }
+
+    /// ✅ Pull `amount` of an allotment from the vault by proving `(claimant, allocation)`
+    /// is a leaf of the escrow's `merkle_root`
+    /// - Leaf = `keccak(claimant || allocation as u64 LE)`, pairs hashed in sorted order
+    /// - Claims may be partial; the claimant's `ClaimRecord` PDA tracks what was taken so
+    ///   the total never exceeds `allocation`
+    /// - Funded only by the owner's `bonus`: a claim can never dip into the tokens
+    ///   backing contributor deposits
+    pub fn claim_with_proof(
+        ctx: Context<ClaimWithProof>,
+        name: String,
+        allocation: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let claimant = ctx.accounts.claimant.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+
+        let leaf = keccak::hashv(&[claimant.as_ref(), &allocation.to_le_bytes()]).to_bytes();
+        require!(
+            esc.merkle_root != [0u8; 32] && verify_merkle_proof(&proof, esc.merkle_root, leaf),
+            EscrowError::InvalidProof
+        );
+
+        let record = &mut ctx.accounts.claim_record;
+        require!(
+            record.claimed_amount < allocation,
+            EscrowError::AlreadyClaimed
+        );
+        let remaining = allocation - record.claimed_amount;
+        require!(
+            amount > 0 && amount <= remaining,
+            EscrowError::NothingToClaim
+        );
+
+        // ✅ Only the bonus pays claims, and the vault must still cover every deposit after
+        let unreserved = ctx
+            .accounts
+            .vault_ata
+            .amount
+            .saturating_sub(esc.deposited_in(&esc.token_mint));
+        require!(
+            amount <= esc.bonus && amount <= unreserved,
+            EscrowError::ClaimUnfunded
+        );
+        esc.bonus -= amount;
+        record.claimed_amount += amount;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_ata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.claimant_ata.to_account_info(),
+            authority: ctx.accounts.vault_auth.to_account_info(),
+        };
+        let escrow_key = esc.key();
+
+        let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[ctx.bumps.vault_auth]];
+        let signer: &[&[&[u8]]] = &[seeds];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)
+    }
+
+    /// ✅ Whether the pool has been paid out, and when (0 until then)
+    pub fn distribution_status(
+        ctx: Context<EscrowInfo>,
+        name: String,
+    ) -> Result<DistributionStatus> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        Ok(DistributionStatus {
+            distributed: esc.distributed,
+            distributed_at: esc.distributed_at,
+        })
+    }
+
+    /// ✅ The escrow's canonical addresses: escrow PDA, vault authority PDA and vault ATA
+    pub fn get_addresses(ctx: Context<EscrowInfo>, name: String) -> Result<EscrowAddresses> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let (vault_auth, _) =
+            Pubkey::find_program_address(&[b"vault-auth", esc.key().as_ref()], ctx.program_id);
+        Ok(EscrowAddresses {
+            escrow: esc.key(),
+            vault_auth,
+            vault_ata: get_associated_token_address_with_program_id(
+                &vault_auth,
+                &esc.token_mint,
+                &esc.token_program,
+            ),
+        })
+    }
+
+    /// ✅ Every owner-configurable setting in one snapshot, for settings UIs
+    pub fn get_config(ctx: Context<EscrowInfo>, name: String) -> Result<EscrowConfig> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        Ok(EscrowConfig {
+            owner: esc.owner,
+            token_mint: esc.token_mint,
+            token_mint_b: esc.token_mint_b,
+            required_deposit: esc.required_deposit,
+            fullness_mode: esc.fullness_mode,
+            target_total: esc.target_total,
+            allowed_modes: esc.allowed_modes,
+            auto_mode: esc.auto_mode,
+            auto_target: esc.auto_target,
+            burn_bps: esc.burn_bps,
+            owner_can_win: esc.owner_can_win,
+            independent_owner: esc.independent_owner,
+            close_on_distribute: esc.close_on_distribute,
+            attester: esc.attester,
+            gate_mint: esc.gate_mint,
+            gate_min_balance: esc.gate_min_balance,
+            merkle_root: esc.merkle_root,
+            fallback_sweep: esc.fallback_sweep,
+            sweep_after_secs: esc.sweep_after_secs,
+            distribute_fallback_secs: esc.distribute_fallback_secs,
+            min_deposit_interval_slots: esc.min_deposit_interval_slots,
+            is_nft: esc.is_nft,
+            price_oracle: esc.price_oracle,
+            oracle_max_age_secs: esc.oracle_max_age_secs,
+            tags: esc.tags.clone(),
+            min_account_age_slots: esc.min_account_age_slots,
+            accepted_mints: esc.accepted_mints.clone(),
+            opt_in_required: esc.opt_in_required,
+            min_payout: esc.min_payout,
+            dust_to_owner: esc.dust_to_owner,
+            reinit_cooldown_secs: esc.reinit_cooldown_secs,
+            owner_is_pda: esc.owner_is_pda,
+            target_whitelist: esc.target_whitelist.clone(),
+            min_unique_payers: esc.min_unique_payers,
+            deposit_deadline: esc.deposit_deadline,
+        })
+    }
+
+    /// ✅ Contributor signs off on a completed distribution (once each)
+    pub fn acknowledge(ctx: Context<Acknowledge>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let contributor = ctx.accounts.contributor.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(esc.distributed, EscrowError::NotDistributed);
+        require!(
+            esc.has_deposited(&contributor),
+            EscrowError::NotAContributor
+        );
+        require!(
+            !esc.acknowledged.contains(&contributor),
+            EscrowError::AlreadyAcknowledged
+        );
+
+        esc.acknowledged.push(contributor);
+        Ok(())
+    }
+
+    /// ✅ Contributor hands their slot, deposit and future payout to `new_owner` before
+    /// distribution; no tokens move
+    /// - `new_owner` must pass the escrow's attestation and gate checks as a depositor would
+    /// - `new_owner` hasn't opted in, whatever the seller did
+    pub fn transfer_slot(
+        ctx: Context<TransferSlot>,
+        name: String,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let contributor = ctx.accounts.contributor.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(!esc.distributed, EscrowError::AlreadyDistributed);
+        require!(
+            esc.pending_plan.is_none(),
+            EscrowError::DistributionInProgress
+        );
+        // ✅ The committed payouts name the current recipients
+        require!(
+            esc.committed_plan.is_none(),
+            EscrowError::PlanAlreadyCommitted
+        );
+
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == contributor)
+            .ok_or(EscrowError::NotAContributor)?;
+        require!(
+            !esc.contributors.contains(&new_owner),
+            EscrowError::SlotTransferConflict
+        );
+        require!(
+            !(esc.independent_owner && esc.owner == new_owner),
+            EscrowError::OwnerCannotDeposit
+        );
+        check_attestation(esc, new_owner, ctx.accounts.attestation.as_ref())?;
+        check_gate(esc, new_owner, ctx.accounts.gate_ata.as_ref())?;
+
+        esc.contributors[i] = new_owner;
+        esc.opted_in.retain(|c| *c != contributor);
+        if esc.last_depositor == contributor {
+            esc.last_depositor = new_owner;
+        }
+        esc.last_activity_at = Clock::get()?.unix_timestamp;
+
+        emit!(SlotTransferred {
+            escrow: esc.key(),
+            from: contributor,
+            to: new_owner,
+        });
+        Ok(())
+    }
+
+    /// ✅ Contributor agrees to receive their share of an `opt_in_required` distribution
+    pub fn opt_in(ctx: Context<Acknowledge>, name: String) -> Result<()> {
+        let esc = &mut ctx.accounts.escrow;
+        let contributor = ctx.accounts.contributor.key();
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        require!(
+            esc.contributors.contains(&contributor),
+            EscrowError::NotAContributor
+        );
+        require!(
+            !esc.opted_in.contains(&contributor),
+            EscrowError::AlreadyOptedIn
+        );
+
+        esc.opted_in.push(contributor);
+        Ok(())
+    }
+
+    /// ✅ Whether every contributor has acknowledged the distribution
+    pub fn all_acknowledged(ctx: Context<EscrowInfo>, name: String) -> Result<bool> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        Ok(esc.distributed
+            && esc
+                .contributors
+                .iter()
+                .all(|c| esc.acknowledged.contains(c)))
+    }
+
+    /// ✅ The amount recorded for `who`'s slot
+    pub fn contributor_amount(ctx: Context<EscrowInfo>, name: String, who: Pubkey) -> Result<u64> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let i = esc
+            .contributors
+            .iter()
+            .position(|c| *c == who)
+            .ok_or(EscrowError::NotAContributor)?;
+        Ok(esc.amounts[i])
+    }
+
+    /// ✅ How full the pool is, in basis points (10_000 = ready to distribute)
+    /// - `Count`: funded slots out of 5
+    /// - `Total`: deposits out of `target_total`, clamped at 10_000
+    pub fn progress_bps(ctx: Context<EscrowInfo>, name: String) -> Result<u16> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let (filled, goal) = match esc.fullness_mode {
+            FullnessMode::Count => (
+                esc.amounts.iter().filter(|amount| **amount > 0).count() as u128,
+                5,
+            ),
+            FullnessMode::Total => (esc.total_deposited() as u128, esc.target_total as u128),
+        };
+        if goal == 0 {
+            return Ok(0);
+        }
+        Ok((filled * 10_000 / goal).min(10_000) as u16)
+    }
+
+    /// ✅ The escrow's owner, for clients that need nothing else
+    pub fn get_owner(ctx: Context<EscrowInfo>, name: String) -> Result<Pubkey> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        Ok(esc.owner)
+    }
+
+    /// ✅ The escrow's deposit mint, for clients that need nothing else
+    pub fn get_mint(ctx: Context<EscrowInfo>, name: String) -> Result<Pubkey> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        Ok(esc.token_mint)
+    }
+
+    /// ✅ Modes of the escrow's completed distributions, oldest first
+    pub fn get_mode_history(ctx: Context<EscrowInfo>, name: String) -> Result<Vec<u8>> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        Ok(esc.mode_history.clone())
+    }
+
+    /// ✅ Total deposited in `mint` across funded slots (0 for a mint nobody used)
+    pub fn deposited_in_mint(ctx: Context<EscrowInfo>, name: String, mint: Pubkey) -> Result<u64> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+        Ok(esc.deposited_in(&mint))
+    }
+
+    /// ✅ Vault balances of several escrows in one call, for portfolio views
+    /// - `remaining_accounts`: `[escrow, vault_ata]` pairs, at most `MAX_BATCH_VAULT_INFO`
+    /// - A vault that was already closed reports 0
+    pub fn batch_vault_info(ctx: Context<BatchVaultInfo>) -> Result<Vec<VaultBalance>> {
+        let pairs = ctx.remaining_accounts;
+        require!(
+            pairs.len().is_multiple_of(2) && pairs.len() / 2 <= MAX_BATCH_VAULT_INFO,
+            EscrowError::InvalidBatchAccounts
+        );
+
+        pairs
+            .chunks(2)
+            .map(|pair| {
+                let (escrow_info, vault_info) = (&pair[0], &pair[1]);
+                require!(
+                    escrow_info.owner == ctx.program_id,
+                    EscrowError::InvalidBatchAccounts
+                );
+                let esc = EscrowState::try_deserialize(&mut &escrow_info.data.borrow()[..])?;
+                require!(esc.is_supported_version(), EscrowError::UnsupportedVersion);
+
+                let (vault_auth, _) = Pubkey::find_program_address(
+                    &[b"vault-auth", escrow_info.key.as_ref()],
+                    ctx.program_id,
+                );
+                require!(
+                    vault_info.key()
+                        == get_associated_token_address_with_program_id(
+                            &vault_auth,
+                            &esc.token_mint,
+                            &esc.token_program,
+                        ),
+                    EscrowError::InvalidVault
+                );
+                let balance = if vault_info.data_is_empty() {
+                    0
+                } else {
+                    token::accessor::amount(vault_info)?
+                };
+
+                Ok(VaultBalance {
+                    escrow: escrow_info.key(),
+                    balance,
+                })
+            })
+            .collect()
+    }
+
+    /// ✅ Dry run: the recipient ATAs `distribute` will look up in `remaining_accounts`
+    /// - Takes the same parameters as `distribute`, so modes 12, 13 and 17 are planned
+    ///   with the `winner_bps` / `lead_amount` the real call will use
+    pub fn validate_recipients(
+        ctx: Context<VaultInfo>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+    ) -> Result<Vec<Pubkey>> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let payouts = distribution_plan(
+            esc,
+            ctx.accounts.vault_ata.amount,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        Ok(payouts
+            .iter()
+            .map(|(recipient, _)| {
+                get_associated_token_address_with_program_id(
+                    recipient,
+                    &esc.token_mint,
+                    &esc.token_program,
+                )
+            })
+            .collect())
+    }
+
+    /// ✅ Vault balance valued in USD through the escrow's `price_oracle`, in units of
+    /// 10^-`USD_VALUE_DECIMALS` dollars. Display only: transfers never consult the oracle.
+    pub fn vault_usd_value(ctx: Context<OracleInfo>, name: String) -> Result<u64> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let (price, expo, published_at) = read_pyth_price(&ctx.accounts.price_oracle)?;
+        require!(
+            Clock::get()?.unix_timestamp - published_at <= esc.oracle_max_age_secs,
+            EscrowError::StaleOracle
+        );
+
+        // ✅ amount * price * 10^(expo - mint decimals + USD decimals)
+        let value = ctx.accounts.vault_ata.amount as u128 * price as u128;
+        let scale = expo
+            .checked_sub(ctx.accounts.mint.decimals as i32)
+            .and_then(|s| s.checked_add(USD_VALUE_DECIMALS))
+            .ok_or(EscrowError::MathOverflow)?;
+        let value = if scale >= 0 {
+            10u128
+                .checked_pow(scale as u32)
+                .and_then(|multiplier| value.checked_mul(multiplier))
+                .ok_or(EscrowError::MathOverflow)?
+        } else {
+            // ✅ A divisor past u128 leaves less than one unit of value
+            10u128
+                .checked_pow(scale.unsigned_abs())
+                .map_or(0, |divisor| value / divisor)
+        };
+        u64::try_from(value).map_err(|_| error!(EscrowError::MathOverflow))
+    }
+
+    /// ✅ Dry run: what `recipient` would receive from `distribute` with these parameters
+    /// right now
+    /// - 0 when the mode pays `recipient` nothing; `burn_bps` is taken off first
+    pub fn simulate_recipient_amount(
+        ctx: Context<VaultInfo>,
+        name: String,
+        mode: u8,
+        target_pubkey: Pubkey,
+        winner_bps: u16,
+        lead_amount: u64,
+        recipient: Pubkey,
+    ) -> Result<u64> {
+        let esc = &ctx.accounts.escrow;
+
+        require!(esc.name == name, EscrowError::NameMismatch);
+
+        let vault = ctx.accounts.vault_ata.amount;
+        let burned = (vault as u128 * esc.burn_bps as u128 / 10_000) as u64;
+        let payouts = distribution_plan(
+            esc,
+            vault - burned,
+            mode,
+            target_pubkey,
+            winner_bps,
+            lead_amount,
+        )?;
+        Ok(payouts
+            .iter()
+            .filter(|(who, _)| *who == recipient)
+            .map(|(_, amount)| amount)
+            .sum())
+    }
+}
+
+/// ✅ Count a new escrow against its owner's registry, when one was passed
+fn register_escrow(
+    esc: &mut EscrowState,
+    registry: Option<&mut Account<OwnerRegistry>>,
+) -> Result<()> {
+    let Some(registry) = registry else {
+        return Ok(());
+    };
+    require!(
+        registry.escrow_count < MAX_ESCROWS_PER_OWNER,
+        EscrowError::OwnerEscrowLimit
+    );
+    registry.escrow_count += 1;
+    esc.in_registry = true;
+    Ok(())
+}
+
+/// ✅ A closed escrow's address stays retired until its tombstone's cooldown runs out,
+/// so contributors never mistake a fresh escrow for the one they knew
+fn check_tombstone(tombstone: &AccountInfo) -> Result<()> {
+    if tombstone.owner != &crate::ID || tombstone.data_is_empty() {
+        return Ok(());
+    }
+    let tombstone = EscrowTombstone::try_deserialize(&mut &tombstone.data.borrow()[..])?;
+    let now = Clock::get()?.unix_timestamp;
+    if now < tombstone.reopens_at {
+        msg!(
+            "Escrow closed at {}; its address reopens at {}",
+            tombstone.closed_at,
+            tombstone.reopens_at
+        );
+        return Err(error!(EscrowError::RecentlyClosed));
+    }
+    Ok(())
+}
+
+/// ✅ Validate `name` and reset `esc` to an empty pool with default settings
+fn init_escrow_state(
+    esc: &mut EscrowState,
+    owner: Pubkey,
+    token_mint: Pubkey,
+    token_program: Pubkey,
+    name: String,
+) -> Result<()> {
+    require!(name.len() <= 32, EscrowError::NameTooLong);
+    // ✅ Printable ASCII (space allowed) with at least one visible character
+    require!(
+        name.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+            && name.bytes().any(|b| b.is_ascii_graphic()),
+        EscrowError::InvalidName
+    );
+
+    esc.version = ESCROW_VERSION;
+    esc.owner = owner;
+    esc.token_mint = token_mint;
+    esc.token_program = token_program;
+    esc.contributors = vec![];
+    esc.amounts = vec![];
+    esc.refunded = vec![];
+    esc.deposited_at = vec![];
+    esc.memos = vec![];
+    esc.deposit_mints = vec![];
+    esc.referrers = vec![];
+    esc.payers = vec![];
+    esc.opted_in = vec![];
+    esc.held_payouts = vec![];
+    esc.committed_plan = None;
+    esc.committed_rule = None;
+    esc.in_registry = false;
+    esc.distributed = false;
+    esc.name = name;
+    esc.last_activity_at = Clock::get()?.unix_timestamp;
+    esc.required_deposit = DEFAULT_REQUIRED_DEPOSIT;
+    Ok(())
+}
+
+/// ✅ Aggregate `(price, expo, publish time)` from a Pyth v2 price account. Only the
+/// header fields used here are checked; the owner configured which account to trust.
+fn read_pyth_price(account: &AccountInfo) -> Result<(i64, i32, i64)> {
+    const MAGIC: u32 = 0xa1b2_c3d4;
+    const STATUS_TRADING: u32 = 1;
+
+    let data = account.try_borrow_data()?;
+    require!(data.len() >= 240, EscrowError::InvalidOracle);
+    let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let i32_at = |at: usize| i32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let i64_at = |at: usize| i64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+
+    let expo = i32_at(20);
+    let published_at = i64_at(96);
+    let price = i64_at(208);
+    require!(
+        u32_at(0) == MAGIC && u32_at(224) == STATUS_TRADING && price > 0,
+        EscrowError::InvalidOracle
+    );
+    Ok((price, expo, published_at))
+}
+
+/// ✅ Newest hash in the `SlotHashes` sysvar: a `u64` entry count, then `(slot, hash)`
+/// entries, most recent first
+fn latest_slot_hash(slot_hashes: &AccountInfo) -> Result<[u8; 32]> {
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= 8 + 8 + 32, EscrowError::NoSlotHash);
+    let count = u64::from_le_bytes(data[..8].try_into().unwrap());
+    require!(count > 0, EscrowError::NoSlotHash);
+    Ok(data[16..48].try_into().unwrap())
+}
+
+/// ✅ An `auto_mode` must be a mode this build knows and one `allowed_modes` permits
+fn check_auto_mode(auto_mode: Option<u8>, allowed_modes: u16) -> Result<()> {
+    if let Some(mode) = auto_mode {
+        check_known_mode(mode)?;
+        require!(
+            allowed_modes == 0 || mode_bit(mode) & allowed_modes != 0,
+            EscrowError::ModeNotAllowed
+        );
+    }
+    Ok(())
+}
+
+/// ✅ `allowed_modes` bit for `mode`; modes past the mask's width map to no bit
+fn mode_bit(mode: u8) -> u16 {
+    1u16.checked_shl(mode as u32).unwrap_or(0)
+}
+
+/// ✅ Every per-slot vector must line up with `contributors`; a mismatch means some
+/// instruction forgot to update one of them, and indexing by slot would go wrong
+fn assert_vectors_aligned(esc: &EscrowState) -> Result<()> {
+    let n = esc.contributors.len();
+    require!(
+        esc.amounts.len() == n
+            && esc.refunded.len() == n
+            && esc.deposited_at.len() == n
+            && esc.memos.len() == n
+            && esc.deposit_mints.len() == n
+            && esc.referrers.len() == n
+            && esc.payers.len() == n,
+        EscrowError::StateCorrupted
+    );
+    Ok(())
+}
+
+/// ✅ What refunding a slot of `amount` funded in `mint` pays out. A vault holding less
+/// than the deposits recorded in its mint (e.g. a fee-on-transfer mint) can't make everyone
+/// whole, so each refund takes the same pro-rata haircut rather than the last contributors
+/// out getting nothing.
+fn refund_share(
+    esc: &EscrowState,
+    mint: Pubkey,
+    vault_amount: u64,
+    amount: u64,
+    accept_haircut: bool,
+) -> Result<u64> {
+    let recorded = esc.deposited_in(&mint);
+    if vault_amount >= recorded {
+        return Ok(amount);
+    }
+    require!(accept_haircut, EscrowError::VaultShortfall);
+    msg!(
+        "Vault holds {} of {} deposited, refund cut pro rata",
+        vault_amount,
+        recorded
+    );
+    Ok((amount as u128 * vault_amount as u128 / recorded as u128) as u64)
+}
+
+/// ✅ For the weighted mode, the vault must hold exactly what the books say: every
+/// recorded deposit plus the owner's bonus. Stray tokens (or a bookkeeping bug) would
+/// otherwise be split by weights that don't account for them.
+fn assert_accounting_matches(esc: &EscrowState, vault_amount: u64, mode: u8) -> Result<()> {
+    if mode != 14 {
+        return Ok(());
+    }
+    let recorded = esc
+        .total_deposited()
+        .checked_add(esc.bonus)
+        .ok_or(EscrowError::DistributionMathError)?;
+    require!(vault_amount == recorded, EscrowError::AccountingMismatch);
+    Ok(())
+}
+
+/// ✅ Validate a first-time deposit and record `contributor` in the pool.
+/// Returns the portion of `amount` actually needed to fill the slot.
+fn admit_contributor(
+    esc: &mut EscrowState,
+    contributor: Pubkey,
+    payer: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    memo: String,
+) -> Result<u64> {
+    let imported_slot = esc.contributors.iter().position(|c| *c == contributor);
+    require!(
+        imported_slot.is_some() || esc.contributors.len() < 5,
+        EscrowError::MaxContributorsReached
+    );
+    // ✅ A pool that is full, being paid out or already paid out takes no more funds:
+    // they would be stranded or change a committed batch plan
+    require!(!esc.distributed, EscrowError::AlreadyDistributed);
+    require!(
+        esc.pending_plan.is_none(),
+        EscrowError::DistributionInProgress
+    );
+    require!(!esc.is_full(), EscrowError::PoolAlreadyFull);
+    require!(
+        !(esc.independent_owner && esc.owner == contributor),
+        EscrowError::OwnerCannotDeposit
+    );
+    let clock = Clock::get()?;
+    require!(
+        esc.deposit_deadline == 0 || clock.unix_timestamp <= esc.deposit_deadline,
+        EscrowError::DepositDeadlinePassed
+    );
+    let required = esc.required_deposit;
+    require!(amount >= required, EscrowError::InvalidDepositAmount);
+
+    let surplus = amount - required;
+    if surplus > 0 {
+        msg!("Surplus of {} left with contributor", surplus);
+    }
+
+    require!(
+        esc.last_deposit_slot == 0
+            || clock.slot
+                >= esc
+                    .last_deposit_slot
+                    .saturating_add(esc.min_deposit_interval_slots),
+        EscrowError::DepositTooSoon
+    );
+    esc.last_deposit_slot = clock.slot;
+    esc.last_depositor = contributor;
+
+    let now = clock.unix_timestamp;
+    match imported_slot {
+        Some(i) => {
+            require!(!esc.refunded[i], EscrowError::AlreadyRefunded);
+            esc.amounts[i] = required;
+            esc.deposited_at[i] = now;
+            esc.memos[i] = memo;
+            esc.deposit_mints[i] = mint;
+            esc.referrers[i] = Pubkey::default();
+            esc.payers[i] = payer;
+        }
+        None => {
+            esc.contributors.push(contributor);
+            esc.amounts.push(required);
+            esc.refunded.push(false);
+            esc.deposited_at.push(now);
+            esc.memos.push(memo);
+            esc.deposit_mints.push(mint);
+            esc.referrers.push(Pubkey::default());
+            esc.payers.push(payer);
+        }
+    }
+    esc.last_activity_at = now;
+    Ok(required)
+}
+
+/// ✅ When the escrow names an attester, `contributor` must hold a live attestation from it
+fn check_attestation(
+    esc: &EscrowState,
+    contributor: Pubkey,
+    attestation: Option<&Account<Attestation>>,
+) -> Result<()> {
+    let Some(attester) = esc.attester else {
+        return Ok(());
+    };
+    let att = attestation.ok_or(EscrowError::NotAttested)?;
+    require!(
+        att.attester == attester
+            && att.subject == contributor
+            && att.expires_at > Clock::get()?.unix_timestamp,
+        EscrowError::NotAttested
+    );
+    if esc.min_account_age_slots > 0 {
+        require!(
+            att.first_seen_slot != 0
+                && Clock::get()?.slot
+                    >= att
+                        .first_seen_slot
+                        .saturating_add(esc.min_account_age_slots),
+            EscrowError::AccountTooNew
+        );
+    }
+    Ok(())
+}
+
+/// ✅ The instruction before this one must be an Ed25519 check of `signature` by
+/// `signer` over `message`, with all data inline in that instruction
+fn verify_permit(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, EscrowError::InvalidPermit);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        ix.program_id == ed25519_program::ID,
+        EscrowError::InvalidPermit
+    );
+
+    // ✅ Layout: [count, padding, offsets (7 x u16 LE)], one signature only
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, EscrowError::InvalidPermit);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let (sig_offset, sig_ix) = (read_u16(2), read_u16(4));
+    let (key_offset, key_ix) = (read_u16(6), read_u16(8));
+    let (msg_offset, msg_size, msg_ix) = (read_u16(10), read_u16(12), read_u16(14));
+    require!(
+        [sig_ix, key_ix, msg_ix]
+            .iter()
+            .all(|i| *i == u16::MAX as usize),
+        EscrowError::InvalidPermit
+    );
+
+    let slice = |offset: usize, len: usize| data.get(offset..offset + len);
+    require!(
+        slice(key_offset, 32) == Some(signer.as_ref())
+            && slice(sig_offset, 64) == Some(signature.as_ref())
+            && slice(msg_offset, msg_size) == Some(message),
+        EscrowError::InvalidPermit
+    );
+    Ok(())
+}
+
+/// ✅ When the escrow is token-gated, `contributor` must hold at least
+/// `gate_min_balance` of `gate_mint` in the passed `gate_ata`
+fn check_gate(
+    esc: &EscrowState,
+    contributor: Pubkey,
+    gate_ata: Option<&InterfaceAccount<TokenAccount>>,
+) -> Result<()> {
+    let Some(gate_mint) = esc.gate_mint else {
+        return Ok(());
+    };
+    let gate = gate_ata.ok_or(EscrowError::GateNotSatisfied)?;
+    require!(
+        gate.mint == gate_mint && gate.owner == contributor && gate.amount >= esc.gate_min_balance,
+        EscrowError::GateNotSatisfied
+    );
+    Ok(())
+}
+
+/// ✅ Fold `proof` up from `leaf` (sorted-pair keccak) and compare with `root`
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+/// ✅ Append to the on-chain audit trail (created on first use)
+fn record_audit(
+    audit: &mut AuditLog,
+    escrow: Pubkey,
+    mode: u8,
+    total: u64,
+    recipient_count: u8,
+) -> Result<()> {
+    require!(
+        audit.records.len() < MAX_AUDIT_RECORDS,
+        EscrowError::AuditLogFull
+    );
+    audit.escrow = escrow;
+    audit.records.push(AuditRecord {
+        mode,
+        total,
+        timestamp: Clock::get()?.unix_timestamp,
+        recipient_count,
+    });
+    Ok(())
+}
+
+/// ✅ Take the payouts whose recipient hasn't opted in out of `payouts` and return them
+/// as held shares. Without `opt_in_required` everything is paid now.
+fn hold_back_unopted(
+    esc: &EscrowState,
+    payouts: &mut Vec<(Pubkey, u64)>,
+) -> Result<Vec<HeldPayout>> {
+    if !esc.opt_in_required {
+        return Ok(vec![]);
+    }
+    let mut held = vec![];
+    payouts.retain(|(recipient, amount)| {
+        let opted_in = esc.opted_in.contains(recipient);
+        if !opted_in {
+            held.push(HeldPayout {
+                recipient: *recipient,
+                amount: *amount,
+            });
+        }
+        opted_in
+    });
+    require!(!payouts.is_empty(), EscrowError::NoOptedInRecipients);
+    Ok(held)
+}
+
+/// ✅ Raise every payout below `min_payout` to it, taking the difference from the
+/// largest payouts first (never pushing them below the floor themselves)
+fn apply_payout_floor(payouts: &mut [(Pubkey, u64)], min_payout: u64) -> Result<()> {
+    if min_payout == 0 {
+        return Ok(());
+    }
+    let total: u128 = payouts.iter().map(|(_, amount)| *amount as u128).sum();
+    require!(
+        total >= min_payout as u128 * payouts.len() as u128,
+        EscrowError::FloorUnaffordable
+    );
+
+    let mut deficit = 0u64;
+    for (_, amount) in payouts.iter_mut() {
+        if *amount < min_payout {
+            deficit += min_payout - *amount;
+            *amount = min_payout;
+        }
+    }
+    while deficit > 0 {
+        let (_, largest) = payouts
+            .iter_mut()
+            .max_by_key(|(_, amount)| *amount)
+            .ok_or(EscrowError::FloorUnaffordable)?;
+        let take = deficit.min(*largest - min_payout);
+        require!(take > 0, EscrowError::FloorUnaffordable);
+        *largest -= take;
+        deficit -= take;
+    }
+    Ok(())
+}
+
+/// ✅ Defensive: a program id passed as the mint, or a mint whose decimals are absurd,
+/// would leave an escrow nobody can deposit into or pay out of
+fn check_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let key = mint.key();
+    require!(
+        key != system_program::ID
+            && key != token::ID
+            && key != anchor_spl::token_2022::ID
+            && key != anchor_spl::associated_token::ID
+            && key != crate::ID,
+        EscrowError::InvalidMint
+    );
+    require!(
+        mint.is_initialized && mint.decimals <= MAX_MINT_DECIMALS,
+        EscrowError::InvalidMint
+    );
+    Ok(())
+}
+
+/// ✅ A mode from a newer (or confused) client gets its own error rather than whatever
+/// this build happens to do with that number
+fn check_known_mode(mode: u8) -> Result<()> {
+    if mode > MAX_KNOWN_MODE {
+        msg!(
+            "Unknown mode {} (this program knows up to {})",
+            mode,
+            MAX_KNOWN_MODE
+        );
+        return Err(error!(EscrowError::UnknownMode));
+    }
+    Ok(())
+}
+
+/// ✅ Compute the `(recipient, amount)` payouts for a distribution mode
+fn distribution_plan(
+    esc: &EscrowState,
+    vault_balance: u64,
+    mode: u8,
+    target_pubkey: Pubkey,
+    winner_bps: u16,
+    lead_amount: u64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    check_known_mode(mode)?;
+    check_unique_payers(esc)?;
+    // ✅ An NFT goes whole to a single winner
+    require!(!esc.is_nft || mode == 0, EscrowError::CannotSplitNft);
+    // ✅ Payouts come from the `token_mint` vault alone; slots funded in another accepted
+    // mint can only be refunded for now
+    require!(
+        esc.deposit_mints.iter().all(|m| *m == esc.token_mint),
+        EscrowError::MixedMintDistribution
+    );
+
+    // ✅ An independent owner can't be any mode's target; otherwise only winner modes care
+    let picks_winner = matches!(mode, 0 | 12 | 13);
+    if esc.owner == target_pubkey && (esc.independent_owner || picks_winner) {
+        require!(
+            esc.owner_can_win && !esc.independent_owner,
+            EscrowError::OwnerCannotWin
+        );
+    }
+
+    match mode {
+        // ✅ Mode 0: Send all funds to one contributor
+        0 => {
+            require!(
+                esc.contributors.contains(&target_pubkey),
+                EscrowError::InvalidTarget
+            );
+            check_target_whitelisted(esc, target_pubkey)?;
+
+            Ok(vec![(target_pubkey, vault_balance)])
+        }
+
+        // ✅ Mode 1: Distribute equally to all except excluded contributor
+        1 => {
+            let recipients: Vec<Pubkey> = esc
+                .contributors
+                .iter()
+                .cloned()
+                .filter(|c| *c != target_pubkey)
+                .collect();
+
+            require!(!recipients.is_empty(), EscrowError::NoEligibleRecipients);
+            // ✅ Every recipient must get at least 1 unit
+            require!(
+                vault_balance >= recipients.len() as u64,
+                EscrowError::BalanceTooLowToSplit
+            );
+            let share = vault_balance / recipients.len() as u64;
+
+            Ok(recipients.into_iter().map(|r| (r, share)).collect())
+        }
+
+        // ✅ Mode 12: Winner takes `winner_bps`, the others split a consolation pool
+        12 => {
+            require!(
+                esc.contributors.contains(&target_pubkey),
+                EscrowError::InvalidTarget
+            );
+            require!(winner_bps <= 10_000, EscrowError::InvalidBps);
+
+            let winner_amount = (vault_balance as u128 * winner_bps as u128 / 10_000) as u64;
+            let consolation = vault_balance - winner_amount;
+            let others: Vec<Pubkey> = esc
+                .contributors
+                .iter()
+                .cloned()
+                .filter(|c| *c != target_pubkey)
+                .collect();
+            if others.is_empty() {
+                return Ok(vec![(target_pubkey, vault_balance)]);
+            }
+
+            let share = consolation / others.len() as u64;
+            let dust = consolation - share * others.len() as u64;
+
+            let mut payouts = vec![(target_pubkey, winner_amount + dust)];
+            payouts.extend(others.into_iter().map(|r| (r, share)));
+            Ok(payouts)
+        }
+
+        // ✅ Mode 13: Fixed amount to the lead, the rest split equally among the others
+        13 => {
+            require!(
+                esc.contributors.contains(&target_pubkey),
+                EscrowError::InvalidTarget
+            );
+            require!(
+                lead_amount <= vault_balance,
+                EscrowError::LeadAmountTooLarge
+            );
+
+            let others: Vec<Pubkey> = esc
+                .contributors
+                .iter()
+                .cloned()
+                .filter(|c| *c != target_pubkey)
+                .collect();
+            if others.is_empty() {
+                return Ok(vec![(target_pubkey, vault_balance)]);
+            }
+
+            let share = (vault_balance - lead_amount) / others.len() as u64;
+
+            let mut payouts = vec![(target_pubkey, lead_amount)];
+            payouts.extend(others.into_iter().map(|r| (r, share)));
+            Ok(payouts)
+        }
+
+        // ✅ Mode 14: Weighted by time in the pool, earlier depositors get more
+        14 => {
+            require!(
+                !esc.contributors.is_empty(),
+                EscrowError::NoEligibleRecipients
+            );
+
+            let now = Clock::get()?.unix_timestamp;
+            let mut weights: Vec<u128> = esc
+                .deposited_at
+                .iter()
+                .map(|t| now.saturating_sub(*t).max(0) as u128)
+                .collect();
+            // ✅ Everyone joined this very second: fall back to an equal split
+            if weights.iter().all(|w| *w == 0) {
+                weights.iter_mut().for_each(|w| *w = 1);
+            }
+            let total_weight: u128 = weights.iter().sum();
+
+            let mut payouts: Vec<(Pubkey, u64)> = esc
+                .contributors
+                .iter()
+                .zip(&weights)
+                .map(|(c, w)| (*c, (vault_balance as u128 * w / total_weight) as u64))
+                .collect();
+            let paid: u64 = payouts.iter().map(|(_, amount)| amount).sum();
+
+            let earliest = earliest_depositor(esc);
+            payouts[earliest].1 += vault_balance - paid;
+            apply_payout_floor(&mut payouts, esc.min_payout)?;
+            Ok(payouts)
+        }
+
+        // ✅ Mode 15: Equal split among everyone, no exclusion
+        15 => {
+            let count = esc.contributors.len() as u64;
+            require!(count > 0, EscrowError::NoEligibleRecipients);
+            require!(vault_balance >= count, EscrowError::BalanceTooLowToSplit);
+
+            let share = vault_balance / count;
+            let dust = vault_balance - share * count;
+
+            let mut payouts: Vec<(Pubkey, u64)> =
+                esc.contributors.iter().map(|c| (*c, share)).collect();
+            payouts[earliest_depositor(esc)].1 += dust;
+            Ok(payouts)
+        }
+
+        // ✅ Mode 17: Bonus for whoever filled the pool last, equal split of the rest
+        17 => {
+            require!(winner_bps <= 10_000, EscrowError::InvalidBps);
+            let last = esc
+                .contributors
+                .iter()
+                .position(|c| *c == esc.last_depositor)
+                .ok_or(EscrowError::NoEligibleRecipients)?;
+
+            let bonus = (vault_balance as u128 * winner_bps as u128 / 10_000) as u64;
+            let rest = vault_balance - bonus;
+            let count = esc.contributors.len() as u64;
+            let share = rest / count;
+            let dust = rest - share * count;
+
+            let mut payouts: Vec<(Pubkey, u64)> =
+                esc.contributors.iter().map(|c| (*c, share)).collect();
+            payouts[last].1 += bonus + dust;
+            Ok(payouts)
+        }
+
+        _ => Err(error!(EscrowError::InvalidMode)),
+    }
+}
+
+/// ✅ Mode 18 payouts: `ranking` is a permutation of the contributors, best first, and
+/// `rank_bps[i]` is rank i's share of the vault; dust goes to first place
+fn ranked_plan(
+    esc: &EscrowState,
+    vault_balance: u64,
+    ranking: &[Pubkey],
+    rank_bps: &[u16],
+) -> Result<Vec<(Pubkey, u64)>> {
+    check_unique_payers(esc)?;
+    require!(!esc.is_nft, EscrowError::CannotSplitNft);
+    require!(
+        esc.deposit_mints.iter().all(|m| *m == esc.token_mint),
+        EscrowError::MixedMintDistribution
+    );
+    require!(
+        !ranking.is_empty()
+            && ranking.len() == esc.contributors.len()
+            && rank_bps.len() == ranking.len()
+            && esc.contributors.iter().all(|c| ranking.contains(c))
+            && ranking
+                .iter()
+                .enumerate()
+                .all(|(i, r)| !ranking[..i].contains(r)),
+        EscrowError::InvalidRanking
+    );
+    require!(
+        rank_bps.iter().map(|b| *b as u32).sum::<u32>() == 10_000,
+        EscrowError::InvalidBps
+    );
+    // ✅ First place is the winner
+    if esc.owner == ranking[0] {
+        require!(
+            esc.owner_can_win && !esc.independent_owner,
+            EscrowError::OwnerCannotWin
+        );
+    }
+
+    let mut payouts: Vec<(Pubkey, u64)> = ranking
+        .iter()
+        .zip(rank_bps)
+        .map(|(r, bps)| (*r, (vault_balance as u128 * *bps as u128 / 10_000) as u64))
+        .collect();
+    let paid: u64 = payouts.iter().map(|(_, amount)| amount).sum();
+    payouts[0].1 += vault_balance - paid;
+    Ok(payouts)
+}
+
+/// ✅ Guards shared by every payout of the main vault: the pool is intact, full, not
+/// paid out or mid-batch, and `mode` is one the owner allows
+fn check_distributable(esc: &EscrowState, mode: u8) -> Result<()> {
+    assert_vectors_aligned(esc)?;
+    require!(!esc.distributed, EscrowError::AlreadyDistributed);
+    require!(
+        esc.pending_plan.is_none(),
+        EscrowError::DistributionInProgress
+    );
+    require!(esc.is_full(), EscrowError::NotFull);
+    require!(esc.mode_allowed(mode), EscrowError::ModeNotAllowed);
+    Ok(())
+}
+
+/// ✅ Sybil resistance: the live (funded, unrefunded) slots must have been paid for by at
+/// least `min_unique_payers` distinct wallets
+fn check_unique_payers(esc: &EscrowState) -> Result<()> {
+    if esc.min_unique_payers == 0 {
+        return Ok(());
+    }
+    let mut payers: Vec<Pubkey> = (0..esc.contributors.len())
+        .filter(|i| esc.amounts[*i] > 0 && !esc.refunded[*i])
+        .map(|i| esc.payers[i])
+        .collect();
+    payers.sort();
+    payers.dedup();
+    require!(
+        payers.len() >= esc.min_unique_payers as usize,
+        EscrowError::InsufficientUniquePayers
+    );
+    Ok(())
+}
+
+/// ✅ With a `target_whitelist`, mode 0 may only pay one of the wallets on it
+fn check_target_whitelisted(esc: &EscrowState, target_pubkey: Pubkey) -> Result<()> {
+    require!(
+        esc.target_whitelist.is_empty() || esc.target_whitelist.contains(&target_pubkey),
+        EscrowError::TargetNotWhitelisted
+    );
+    Ok(())
+}
+
+/// ✅ Slot of the contributor who deposited first, ties going to the lowest pubkey.
+/// Dust follows this rather than storage order, so reordering `contributors` never
+/// changes who receives it. Callers make sure the pool isn't empty.
+fn earliest_depositor(esc: &EscrowState) -> usize {
+    (0..esc.contributors.len())
+        .min_by_key(|i| (esc.deposited_at[*i], esc.contributors[*i]))
+        .unwrap_or_default()
+}
+
+/// ✅ Burn the escrow's `burn_bps` cut of the vault; returns the amount burned
+fn burn_cut(accounts: &Distribute, vault_auth_bump: u8) -> Result<u64> {
+    burn_from_vault(
+        &accounts.escrow,
+        &accounts.vault_ata,
+        &accounts.mint,
+        &accounts.vault_auth,
+        &accounts.token_program,
+        vault_auth_bump,
+    )
+}
+
+/// ✅ `burn_cut` for callers outside the `Distribute` context
+fn burn_from_vault<'info>(
+    escrow: &Account<'info, EscrowState>,
+    vault_ata: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault_auth: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    vault_auth_bump: u8,
+) -> Result<u64> {
+    let amount = (vault_ata.amount as u128 * escrow.burn_bps as u128 / 10_000) as u64;
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    let escrow_key = escrow.key();
+    let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let signer: &[&[&[u8]]] = &[seeds];
+
+    let cpi_accounts = Burn {
+        mint: mint.to_account_info(),
+        from: vault_ata.to_account_info(),
+        authority: vault_auth.to_account_info(),
+    };
+    let cpi_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::burn(cpi_ctx, amount)?;
+
+    emit!(Burned {
+        escrow: escrow_key,
+        amount,
+    });
+    Ok(amount)
+}
+
+/// ✅ With `dust_to_owner`, send the owner whatever the split left in the vault beyond
+/// `keep` (shares still held for recipients)
+fn sweep_dust_to_owner(accounts: &Distribute, vault_auth_bump: u8, keep: u64) -> Result<()> {
+    if !accounts.escrow.dust_to_owner {
+        return Ok(());
+    }
+    let dust = token::accessor::amount(&accounts.vault_ata.to_account_info())?.saturating_sub(keep);
+    if dust == 0 {
+        return Ok(());
+    }
+    let owner_ata = accounts
+        .owner_ata
+        .as_ref()
+        .ok_or(EscrowError::MissingOwnerAta)?;
+
+    let escrow_key = accounts.escrow.key();
+    let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let signer: &[&[&[u8]]] = &[seeds];
+
+    let cpi_accounts = TransferChecked {
+        from: accounts.vault_ata.to_account_info(),
+        mint: accounts.mint.to_account_info(),
+        to: owner_ata.to_account_info(),
+        authority: accounts.vault_auth.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+    token_interface::transfer_checked(cpi_ctx, dust, accounts.mint.decimals)?;
+    msg!("{} tokens of dust sent to the owner", dust);
+    Ok(())
+}
+
+/// ✅ With `close_on_distribute`, close a vault the distribution emptied and return its
+/// rent to the owner. A vault still holding dust stays open.
+fn close_vault_if_empty(accounts: &Distribute, vault_auth_bump: u8) -> Result<()> {
+    close_vault(
+        &accounts.escrow,
+        &accounts.vault_ata,
+        &accounts.owner.to_account_info(),
+        &accounts.vault_auth,
+        &accounts.token_program,
+        vault_auth_bump,
+    )
+}
+
+/// ✅ `close_vault_if_empty` for callers outside the `Distribute` context
+fn close_vault<'info>(
+    escrow: &Account<'info, EscrowState>,
+    vault_ata: &InterfaceAccount<'info, TokenAccount>,
+    owner: &AccountInfo<'info>,
+    vault_auth: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    vault_auth_bump: u8,
+) -> Result<()> {
+    if !escrow.close_on_distribute {
+        return Ok(());
+    }
+    let remaining = token::accessor::amount(&vault_ata.to_account_info())?;
+    if remaining > 0 {
+        msg!("{} tokens of dust left, vault stays open", remaining);
+        return Ok(());
+    }
+
+    let escrow_key = escrow.key();
+    let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let signer: &[&[&[u8]]] = &[seeds];
+
+    let cpi_accounts = CloseAccount {
+        account: vault_ata.to_account_info(),
+        destination: owner.clone(),
+        authority: vault_auth.to_account_info(),
+    };
+    let cpi_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::close_account(cpi_ctx)
+}
+
+/// ✅ Commitment to a batched distribution: its parameters and every payout in order
+fn plan_hash(
+    mode: u8,
+    target_pubkey: Pubkey,
+    winner_bps: u16,
+    lead_amount: u64,
+    payouts: &[(Pubkey, u64)],
+) -> [u8; 32] {
+    let mut preimage = vec![mode];
+    preimage.extend_from_slice(target_pubkey.as_ref());
+    preimage.extend_from_slice(&winner_bps.to_le_bytes());
+    preimage.extend_from_slice(&lead_amount.to_le_bytes());
+    for (recipient, amount) in payouts {
+        preimage.extend_from_slice(recipient.as_ref());
+        preimage.extend_from_slice(&amount.to_le_bytes());
+    }
+    keccak::hash(&preimage).to_bytes()
+}
+
+/// ✅ Once `commit_plan` has run, the payouts about to be made must hash to the commitment
+fn check_committed_plan(
+    esc: &EscrowState,
+    mode: u8,
+    target_pubkey: Pubkey,
+    winner_bps: u16,
+    lead_amount: u64,
+    payouts: &[(Pubkey, u64)],
+) -> Result<()> {
+    if let Some(committed) = esc.committed_plan {
+        require!(
+            plan_hash(mode, target_pubkey, winner_bps, lead_amount, payouts) == committed,
+            EscrowError::PlanHashMismatch
+        );
+    }
+    Ok(())
+}
+
+/// ✅ Transfer each payout from the vault to the recipient's ATA in `remaining_accounts`
+#[allow(clippy::too_many_arguments)]
+fn pay_out<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    vault_ata: &InterfaceAccount<'info, TokenAccount>,
+    vault_auth: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    escrow_key: Pubkey,
+    vault_auth_bump: u8,
+    mint: &InterfaceAccount<'info, Mint>,
+    payouts: &[(Pubkey, u64)],
+) -> Result<()> {
+    // ✅ Shares can never add up to more than the vault holds (read live: a burn may
+    // already have run in this instruction)
+    let vault_before = token::accessor::amount(&vault_ata.to_account_info())?;
+    let total = payouts
+        .iter()
+        .try_fold(0u64, |acc, (_, amount)| acc.checked_add(*amount))
+        .ok_or(EscrowError::DistributionMathError)?;
+    require!(total <= vault_before, EscrowError::DistributionMathError);
+
+    let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let signer: &[&[&[u8]]] = &[seeds];
+
+    for (recipient, amount) in payouts {
+        let recipient_ata = get_associated_token_address_with_program_id(
+            recipient,
+            &mint.key(),
+            &token_program.key(),
+        );
+
+        // ✅ Find matching AccountInfo passed in ctx.remaining_accounts
+        let ata_info = remaining_accounts
+            .iter()
+            .find(|acc| acc.key() == recipient_ata)
+            .ok_or(EscrowError::MissingRecipientAta)?;
+        // ✅ Paying the vault back into itself would strand the share
+        require!(ata_info.key() != vault_ata.key(), EscrowError::SelfTransfer);
+
+        // ✅ The address alone isn't enough: it must be a live token account for the recipient
+        let recipient_account = InterfaceAccount::<TokenAccount>::try_from(ata_info)
+            .map_err(|_| error!(EscrowError::InvalidRecipientAta))?;
+        require!(
+            recipient_account.mint == mint.key() && recipient_account.owner == *recipient,
+            EscrowError::InvalidRecipientAta
+        );
+
+        let cpi_accounts = TransferChecked {
+            from: vault_ata.to_account_info(),
+            mint: mint.to_account_info(),
+            to: ata_info.clone(),
+            authority: vault_auth.clone(),
+        };
+        let cpi_ctx =
+            CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+
+        token_interface::transfer_checked(cpi_ctx, *amount, mint.decimals)?;
+    }
+
+    // ✅ Whatever wasn't paid out must still be sitting in the vault
+    let vault_after = token::accessor::amount(&vault_ata.to_account_info())?;
+    require!(
+        vault_after == vault_before - total,
+        EscrowError::DistributionMathError
+    );
+
+    Ok(())
+}
+
+/// ✅ Pay each share out as native SOL: move the total into a temporary wSOL account,
+/// close it into the vault authority, then send the lamports on to each wallet.
+/// `remaining_accounts` starts with the temporary account, then the recipient wallets.
+fn pay_out_native<'info>(
+    accounts: &Distribute<'info>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    vault_auth_bump: u8,
+    program_id: &Pubkey,
+    payouts: &[(Pubkey, u64)],
+) -> Result<()> {
+    let total = payouts
+        .iter()
+        .try_fold(0u64, |acc, (_, amount)| acc.checked_add(*amount))
+        .ok_or(EscrowError::DistributionMathError)?;
+    let vault_before = token::accessor::amount(&accounts.vault_ata.to_account_info())?;
+    require!(total <= vault_before, EscrowError::DistributionMathError);
+
+    let escrow_key = accounts.escrow.key();
+    let (unwrap_key, unwrap_bump) =
+        Pubkey::find_program_address(&[b"unwrap", escrow_key.as_ref()], program_id);
+    let (unwrap, wallets) = remaining_accounts
+        .split_first()
+        .ok_or(EscrowError::InvalidUnwrapAccount)?;
+    require!(
+        unwrap.key() == unwrap_key,
+        EscrowError::InvalidUnwrapAccount
+    );
+
+    let auth_seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let unwrap_seeds: &[&[u8]] = &[b"unwrap", escrow_key.as_ref(), &[unwrap_bump]];
+    let auth_signer: &[&[&[u8]]] = &[auth_seeds];
+    let token_program = accounts.token_program.to_account_info();
+    let system = accounts.system_program.to_account_info();
+
+    let rent = Rent::get()?.minimum_balance(token::TokenAccount::LEN);
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system.clone(),
+            system_program::CreateAccount {
+                from: accounts.owner.to_account_info(),
+                to: unwrap.clone(),
+            },
+            &[unwrap_seeds],
+        ),
+        rent,
+        token::TokenAccount::LEN as u64,
+        &accounts.token_program.key(),
+    )?;
+    token_interface::initialize_account3(CpiContext::new(
+        token_program.clone(),
+        InitializeAccount3 {
+            account: unwrap.clone(),
+            mint: accounts.mint.to_account_info(),
+            authority: accounts.vault_auth.to_account_info(),
+        },
+    ))?;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TransferChecked {
+                from: accounts.vault_ata.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                to: unwrap.clone(),
+                authority: accounts.vault_auth.to_account_info(),
+            },
+            auth_signer,
+        ),
+        total,
+        accounts.mint.decimals,
+    )?;
+
+    // ✅ Closing a native account releases its rent and wrapped lamports alike
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program,
+        CloseAccount {
+            account: unwrap.clone(),
+            destination: accounts.vault_auth.to_account_info(),
+            authority: accounts.vault_auth.to_account_info(),
+        },
+        auth_signer,
+    ))?;
+
+    for (recipient, amount) in payouts {
+        let wallet = wallets
+            .iter()
+            .find(|acc| acc.key() == *recipient)
+            .ok_or(EscrowError::MissingRecipientWallet)?;
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                system.clone(),
+                system_program::Transfer {
+                    from: accounts.vault_auth.to_account_info(),
+                    to: wallet.clone(),
+                },
+                auth_signer,
+            ),
+            *amount,
+        )?;
+    }
+
+    // ✅ The owner fronted the temporary account's rent
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system,
+            system_program::Transfer {
+                from: accounts.vault_auth.to_account_info(),
+                to: accounts.owner.to_account_info(),
+            },
+            auth_signer,
+        ),
+        rent,
+    )
+}
+
+/// ✅ Swap each payout into the output mint on its way to the recipient (see
+/// `distribute_swapped` for the account layout and the swap CPI boundary)
+fn pay_out_swapped<'info>(
+    accounts: &DistributeSwapped<'info>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    vault_auth_bump: u8,
+    swap_auth_bump: u8,
+    min_amount_out: u64,
+    payouts: &[(Pubkey, u64)],
+) -> Result<()> {
+    require!(
+        remaining_accounts.len() >= payouts.len(),
+        EscrowError::MissingRecipientAta
+    );
+    let (recipient_atas, pool_accounts) = remaining_accounts.split_at(payouts.len());
+    let output_mint = accounts.output_mint.key();
+
+    let escrow_key = accounts.escrow.key();
+    let seeds: &[&[u8]] = &[b"vault-auth", escrow_key.as_ref(), &[vault_auth_bump]];
+    let vault_signer: &[&[&[u8]]] = &[seeds];
+    let swap_seeds: &[&[u8]] = &[b"swap-auth", escrow_key.as_ref(), &[swap_auth_bump]];
+    let source_info = accounts.swap_source.to_account_info();
+    let swap_discriminator = &hash::hash(b"global:swap").to_bytes()[..8];
+
+    for (recipient, amount_in) in payouts {
+        let recipient_ata = get_associated_token_address_with_program_id(
+            recipient,
+            &output_mint,
+            &accounts.token_program.key(),
+        );
+        let ata_info = recipient_atas
+            .iter()
+            .find(|acc| acc.key() == recipient_ata)
+            .ok_or(EscrowError::MissingRecipientAta)?;
+        let recipient_account = InterfaceAccount::<TokenAccount>::try_from(ata_info)
+            .map_err(|_| error!(EscrowError::InvalidRecipientAta))?;
+        require!(
+            recipient_account.mint == output_mint && recipient_account.owner == *recipient,
+            EscrowError::InvalidRecipientAta
+        );
+
+        // ✅ Only the share itself is exposed to the swap program
+        let source_before = token::accessor::amount(&source_info)?;
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: accounts.vault_ata.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                to: source_info.clone(),
+                authority: accounts.vault_auth.to_account_info(),
+            },
+            vault_signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, *amount_in, accounts.mint.decimals)?;
+
+        let mut metas = vec![
+            AccountMeta::new(source_info.key(), false),
+            AccountMeta::new(ata_info.key(), false),
+            AccountMeta::new_readonly(accounts.swap_auth.key(), true),
+            AccountMeta::new_readonly(accounts.token_program.key(), false),
+        ];
+        metas.extend(pool_accounts.iter().map(|acc| AccountMeta {
+            pubkey: acc.key(),
+            is_signer: acc.is_signer,
+            is_writable: acc.is_writable,
+        }));
+        let mut data = swap_discriminator.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let mut infos = vec![
+            source_info.clone(),
+            ata_info.clone(),
+            accounts.swap_auth.to_account_info(),
+            accounts.token_program.to_account_info(),
+        ];
+        infos.extend(pool_accounts.iter().cloned());
+
+        let out_before = recipient_account.amount;
+        invoke_signed(
+            &Instruction {
+                program_id: accounts.swap_program.key(),
+                accounts: metas,
+                data,
+            },
+            &infos,
+            &[swap_seeds],
+        )?;
+
+        // ✅ The whole share was swapped, none of it left behind in the scratch account
+        require!(
+            token::accessor::amount(&source_info)? == source_before,
+            EscrowError::SwapMismatch
+        );
+        let out_after = token::accessor::amount(ata_info)?;
+        require!(
+            out_after.saturating_sub(out_before) >= min_amount_out,
+            EscrowError::SlippageExceeded
+        );
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"escrow", owner.key().as_ref(), name.as_bytes()],
+        bump,
+        space = EscrowState::SPACE
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+
+    // ✅ A pre-created ATA must not brick init, but it can't carry a balance in
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = vault_auth,
+        constraint = vault_ata.amount == 0 @ EscrowError::VaultNotEmpty
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"owner-reg", owner.key().as_ref()], bump)]
+    pub owner_registry: Option<Account<'info, OwnerRegistry>>,
+
+    #[account(seeds = [b"tombstone", escrow.key().as_ref()], bump)]
+    /// CHECK: `EscrowTombstone` PDA; empty unless this address was closed before
+    pub tombstone: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_name: String)]
+pub struct CreateTemplate<'info> {
+    #[account(
+        init,
+        payer = operator,
+        seeds = [b"template", operator.key().as_ref(), template_name.as_bytes()],
+        bump,
+        space = 8 + 32 + (1 + 32) + 8 + 1 + 1 + (1 + 32) + (1 + 32) + 8 + 8 + 1 + 2 + 1 + 8 + 2 + (1 + 1) + 32
+    )]
+    pub template: Account<'info, TemplateConfig>,
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, template_name: String)]
+pub struct InitializeFromTemplate<'info> {
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"escrow", owner.key().as_ref(), name.as_bytes()],
+        bump,
+        space = EscrowState::SPACE
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"template", owner.key().as_ref(), template_name.as_bytes()], bump)]
+    pub template: Account<'info, TemplateConfig>,
+
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+
+    // ✅ A pre-created ATA must not brick init, but it can't carry a balance in
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = vault_auth,
+        constraint = vault_ata.amount == 0 @ EscrowError::VaultNotEmpty
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"owner-reg", owner.key().as_ref()], bump)]
+    pub owner_registry: Option<Account<'info, OwnerRegistry>>,
+
+    #[account(seeds = [b"tombstone", escrow.key().as_ref()], bump)]
+    /// CHECK: `EscrowTombstone` PDA; empty unless this address was closed before
+    pub tombstone: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = contributor_ata.owner == contributor.key()
+            && contributor_ata.mint == mint.key() @ EscrowError::InvalidSourceAccount
+    )]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = !vault_ata.is_frozen() @ EscrowError::VaultFrozen)]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        constraint = escrow.accepts_mint(&mint.key()) @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub gate_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Refund<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub contributor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = contributor_ata.owner == contributor.key()
+            && contributor_ata.mint == mint.key() @ EscrowError::InvalidSourceAccount
+    )]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = refund_destination.mint == mint.key() @ EscrowError::InvalidRecipientAta
+    )]
+    pub refund_destination: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        constraint = escrow.accepts_mint(&mint.key()) @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, who: Pubkey)]
+pub struct EjectContributor<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = contributor_ata.owner == who
+            && contributor_ata.mint == mint.key() @ EscrowError::InvalidRecipientAta
+    )]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        constraint = escrow.accepts_mint(&mint.key()) @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct OwnerTopup<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = owner_ata.owner == owner.key()
+            && owner_ata.mint == escrow.token_mint @ EscrowError::InvalidSourceAccount
+    )]
+    pub owner_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClaimWithProof<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = claimant,
+        seeds = [b"claim", escrow.key().as_ref(), claimant.key().as_ref()],
+        bump,
+        space = 8 + 8
+    )]
+    pub claim_record: Account<'info, ClaimRecord>,
+    #[account(
+        mut,
+        constraint = claimant_ata.owner == claimant.key()
+            && claimant_ata.mint == escrow.token_mint @ EscrowError::InvalidRecipientAta
+    )]
+    pub claimant_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DepositDelegated<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub delegate: Signer<'info>,
+    #[account(mut, constraint = contributor_ata.mint == escrow.token_mint @ EscrowError::UnknownMint)]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program,
+        constraint = !vault_ata.is_frozen() @ EscrowError::VaultFrozen
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub gate_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DepositWithPermit<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    #[account(mut, constraint = contributor_ata.mint == escrow.token_mint @ EscrowError::UnknownMint)]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program,
+        constraint = !vault_ata.is_frozen() @ EscrowError::VaultFrozen
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub gate_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read for the Ed25519 permit check
+    pub instructions: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"permit-nonce", contributor_ata.owner.as_ref()],
+        bump,
+        space = 8 + 8
+    )]
+    pub permit_nonce: Account<'info, PermitNonce>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct RegisterReferralCode<'info> {
+    #[account(
+        init,
+        payer = referrer,
+        seeds = [b"ref", code.as_bytes()],
+        bump,
+        space = 8 + 32 + 4 + MAX_REFERRAL_CODE_LEN
+    )]
+    pub referral_code: Account<'info, ReferralCode>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, amount: u64, code: String)]
+pub struct DepositWithCode<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub contributor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = contributor_ata.owner == contributor.key()
+            && contributor_ata.mint == escrow.token_mint @ EscrowError::InvalidSourceAccount
+    )]
+    pub contributor_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program,
+        constraint = !vault_ata.is_frozen() @ EscrowError::VaultFrozen
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"ref", code.as_bytes()], bump)]
+    /// CHECK: `ReferralCode` PDA for `code`; may be empty, which the handler reports
+    pub referral_code: AccountInfo<'info>,
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub gate_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitOwnerRegistry<'info> {
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"owner-reg", owner.key().as_ref()],
+        bump,
+        space = 8 + 32 + 4
+    )]
+    pub owner_registry: Account<'info, OwnerRegistry>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(subject: Pubkey)]
+pub struct Attest<'info> {
+    #[account(
+        init_if_needed,
+        payer = attester,
+        seeds = [b"attestation", attester.key().as_ref(), subject.as_ref()],
+        bump,
+        space = 8 + 32 + 32 + 8 + 8
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(mut)]
+    pub attester: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Distribute<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program,
+        constraint = !vault_ata.is_frozen() @ EscrowError::VaultFrozen
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority; writable so `distribute_native` can route lamports through it
+    #[account(mut, seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        mut,
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"audit", escrow.key().as_ref()],
+        bump,
+        space = 8 + 32 + 4 + MAX_AUDIT_RECORDS * (1 + 8 + 8 + 1)
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    pub target_escrow: Option<Account<'info, EscrowState>>,
+    #[account(mut)]
+    pub target_vault_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_ata.owner == owner.key()
+            && owner_ata.mint == escrow.token_mint @ EscrowError::InvalidRecipientAta
+    )]
+    pub owner_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = sysvar::slot_hashes::ID)]
+    /// CHECK: SlotHashes sysvar, read by `reveal_and_distribute` for its draw
+    pub slot_hashes: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DistributeBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: rent destination when `close_on_distribute` closes the vault
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: AccountInfo<'info>,
+    #[account(mut, seeds = [b"audit", escrow.key().as_ref()], bump)]
+    pub audit_log: Account<'info, AuditLog>,
+    pub caller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct FallbackDistribute<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        mut,
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub contributor: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DistributeSwapped<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        mut,
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub output_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"audit", escrow.key().as_ref()],
+        bump,
+        space = 8 + 32 + 4 + MAX_AUDIT_RECORDS * (1 + 8 + 8 + 1)
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(seeds = [b"swap-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority over `swap_source` only; the one signer the swap program gets
+    pub swap_auth: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = swap_auth,
+        associated_token::token_program = token_program
+    )]
+    pub swap_source: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: swap program chosen by the owner; it only ever signs with `swap_auth`,
+    /// so it can reach nothing beyond the share moved into `swap_source`
+    #[account(
+        executable,
+        constraint = swap_program.key() != token::ID
+            && swap_program.key() != anchor_spl::token_2022::ID
+            && swap_program.key() != crate::ID @ EscrowError::InvalidSwapProgram
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CloseEscrow<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            vault_auth.key,
+            &escrow.token_mint,
+            token_program.key
+        ) @ EscrowError::InvalidVault
+    )]
+    /// CHECK: the escrow's vault address; closed with the escrow if it still exists
+    pub vault_ata: UncheckedAccount<'info>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(mut, seeds = [b"owner-reg", owner.key().as_ref()], bump)]
+    pub owner_registry: Option<Account<'info, OwnerRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"tombstone", escrow.key().as_ref()],
+        bump,
+        space = 8 + 32 + 8 + 8
+    )]
+    pub tombstone: Option<Account<'info, EscrowTombstone>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SweepToFallback<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub fallback_ata: InterfaceAccount<'info, TokenAccount>,
+    pub caller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct VaultInfo<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CommitPlan<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
     pub escrow: Account<'info, EscrowState>,
-    #[account(mut)]
-    pub vault_ata: Account<'info, TokenAccount>,
+    #[account(address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct OracleInfo<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: must be the escrow's configured `price_oracle`; parsed by `read_pyth_price`
+    #[account(
+        constraint = escrow.price_oracle == Some(price_oracle.key()) @ EscrowError::InvalidOracle
+    )]
+    pub price_oracle: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct Acknowledge<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub contributor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub recipient: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient_ata.owner == recipient.key()
+            && recipient_ata.mint == escrow.token_mint @ EscrowError::InvalidRecipientAta
+    )]
+    pub recipient_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = escrow.token_mint,
+        associated_token::authority = vault_auth,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(
+        address = escrow.token_mint @ EscrowError::UnknownMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct TransferSlot<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    pub contributor: Signer<'info>,
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub gate_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// ✅ Everything `batch_vault_info` reads comes through `remaining_accounts`
+#[derive(Accounts)]
+pub struct BatchVaultInfo {}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct EscrowInfo<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion
+    )]
+    pub escrow: Account<'info, EscrowState>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct OwnerUpdate<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+}
+
+/// ✅ `OwnerUpdate` without the frozen check, so a frozen escrow can be thawed
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct FreezeUpdate<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitVaultB<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
+    pub owner: Signer<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
     #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
     /// CHECK: PDA authority
     pub vault_auth: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_b,
+        associated_token::authority = vault_auth,
+        constraint = vault_ata_b.amount == 0 @ EscrowError::VaultNotEmpty
+    )]
+    pub vault_ata_b: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DepositB<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
     #[account(mut)]
+    pub contributor: Signer<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = mint_b, associated_token::authority = contributor, associated_token::token_program = token_program)]
+    pub contributor_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, associated_token::mint = mint_b, associated_token::authority = vault_auth, associated_token::token_program = token_program)]
+    pub vault_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DistributeB<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.owner.as_ref(), name.as_bytes()],
+        bump,
+        constraint = escrow.is_supported_version() @ EscrowError::UnsupportedVersion,
+        constraint = !escrow.frozen @ EscrowError::EscrowFrozen
+    )]
+    pub escrow: Account<'info, EscrowState>,
+    #[account(mint::token_program = token_program)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = mint_b, associated_token::authority = vault_auth, associated_token::token_program = token_program)]
+    pub vault_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"vault-auth", escrow.key().as_ref()], bump)]
+    /// CHECK: PDA authority
+    pub vault_auth: AccountInfo<'info>,
+    #[account(mut, address = escrow.owner @ EscrowError::Unauthorized)]
     pub owner: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"audit", escrow.key().as_ref()],
+        bump,
+        space = 8 + 32 + 4 + MAX_AUDIT_RECORDS * (1 + 8 + 8 + 1)
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
 #[account]
 pub struct EscrowState {
+    pub version: u8,
     pub owner: Pubkey,
     pub token_mint: Pubkey,
     pub contributors: Vec<Pubkey>,
     pub distributed: bool,
     pub name: String,
+    pub fallback_sweep: Option<Pubkey>,
+    pub sweep_after_secs: i64,
+    pub last_activity_at: i64,
+    pub token_mint_b: Option<Pubkey>,
+    pub distributed_b: bool,
+    pub owner_can_win: bool,
+    pub independent_owner: bool,
+    pub attester: Option<Pubkey>,
+    /// Deposited amount per slot, parallel to `contributors` (0 = imported, unfunded)
+    pub amounts: Vec<u64>,
+    /// Contributors left out by `force_distribute_skipping`
+    pub skipped: Vec<Pubkey>,
+    /// `keccak(seed)` committed by `commit_winner`
+    pub winner_commitment: Option<[u8; 32]>,
+    /// Slots whose deposit was returned by `refund`, parallel to `contributors`
+    pub refunded: Vec<bool>,
+    /// Mint depositors must hold to join (token-gated pools)
+    pub gate_mint: Option<Pubkey>,
+    /// Minimum `gate_mint` balance required when gated
+    pub gate_min_balance: u64,
+    /// Tokens each contributor deposits to fill a slot
+    pub required_deposit: u64,
+    /// Unix time each slot was funded, parallel to `contributors` (0 = unfunded)
+    pub deposited_at: Vec<i64>,
+    /// Close the vault once a distribution leaves it empty
+    pub close_on_distribute: bool,
+    /// Root of the `(claimant, amount)` tree for `claim_with_proof`
+    pub merkle_root: [u8; 32],
+    /// Tokens the owner added through `owner_topup` (not tied to any slot), less what
+    /// `claim_with_proof` has paid out of them
+    pub bonus: u64,
+    /// Target committed by `set_target` for `distribute_stored`
+    pub stored_target: Option<Pubkey>,
+    /// Deposit memo per slot, parallel to `contributors` (empty = none)
+    pub memos: Vec<String>,
+    /// Unix time the pool was paid out (0 until `distributed`)
+    pub distributed_at: i64,
+    /// Share of the vault (bps) burned before each `Distribute`-based payout
+    pub burn_bps: u16,
+    /// Unused: the runtime already refuses to re-enter a program through its own CPIs.
+    /// Always false; kept so the account layout doesn't shift
+    pub locked: bool,
+    /// How `is_full` decides the pool is ready to distribute
+    pub fullness_mode: FullnessMode,
+    /// Deposit total that fills the pool under `FullnessMode::Total`
+    pub target_total: u64,
+    /// Mode the filling deposit distributes with, if any
+    pub auto_mode: Option<u8>,
+    /// `target_pubkey` for the automatic distribution
+    pub auto_target: Pubkey,
+    /// Seconds a full pool waits on the owner before contributors may distribute it
+    pub distribute_fallback_secs: i64,
+    /// Bitmask of permitted distribution modes (bit `m` for mode `m`, 0 for all)
+    pub allowed_modes: u16,
+    /// Slots required between consecutive deposits (0 for no limit)
+    pub min_deposit_interval_slots: u64,
+    /// Slot of the latest deposit (0 before the first)
+    pub last_deposit_slot: u64,
+    /// Pool of a single NFT: one unit deposited, paid out whole by mode 0
+    pub is_nft: bool,
+    /// Contributors who signed off on the distribution
+    pub acknowledged: Vec<Pubkey>,
+    /// Pyth price account used by `vault_usd_value`
+    pub price_oracle: Option<Pubkey>,
+    /// Oldest oracle price, in seconds, `vault_usd_value` accepts
+    pub oracle_max_age_secs: i64,
+    /// Hash committed by `begin_distribution` while a batched distribution is under way
+    pub pending_plan: Option<[u8; 32]>,
+    /// Vault balance the pending plan splits
+    pub plan_total: u64,
+    /// Recipients of the pending plan paid so far (a plan pays at most the 5 slots)
+    pub plan_cursor: u8,
+    /// Token program that owns `token_mint` (SPL Token or Token-2022)
+    pub token_program: Pubkey,
+    /// Operator-defined category ids, at most `MAX_TAGS`
+    pub tags: Vec<u32>,
+    /// Contributor whose deposit was accepted most recently (mode 17's bonus recipient)
+    pub last_depositor: Pubkey,
+    /// Minimum wallet age in slots, per the attester's `first_seen_slot` (0 disables)
+    pub min_account_age_slots: u64,
+    /// Mints accepted for deposits besides `token_mint`, at most `MAX_ACCEPTED_MINTS`
+    pub accepted_mints: Vec<Pubkey>,
+    /// Mint each slot was funded in (parallel to `contributors`)
+    pub deposit_mints: Vec<Pubkey>,
+    /// `distribute` only pays recipients in `opted_in`
+    pub opt_in_required: bool,
+    /// Contributors who called `opt_in`
+    pub opted_in: Vec<Pubkey>,
+    /// Shares `distribute` held back for recipients who hadn't opted in yet
+    pub held_payouts: Vec<HeldPayout>,
+    /// Hash of the payouts fixed by `commit_plan`
+    pub committed_plan: Option<[u8; 32]>,
+    /// When `commit_plan` ran
+    pub plan_committed_at: i64,
+    /// Counted in the owner's `OwnerRegistry`, so closing must pass it to uncount
+    pub in_registry: bool,
+    /// Least a mode 14 recipient is paid (0 disables)
+    pub min_payout: u64,
+    /// `distribute` sends the split's remainder to the owner
+    pub dust_to_owner: bool,
+    /// Referrer each slot named through `deposit_with_code` (default key for none;
+    /// parallel to `contributors`)
+    pub referrers: Vec<Pubkey>,
+    /// How long the address stays retired after `close_escrow` (0 disables)
+    pub reinit_cooldown_secs: i64,
+    /// Schedule set by `distribute_vesting`
+    pub vesting: Option<VestingSchedule>,
+    /// Each recipient's mode 19 allocation and how much of it `claim_vested` has paid
+    pub vesting_grants: Vec<VestingGrant>,
+    /// The owner is a PDA that signs through its program's CPI
+    pub owner_is_pda: bool,
+    /// Wallets mode 0 may pay out to (empty allows any contributor)
+    pub target_whitelist: Vec<Pubkey>,
+    /// Distinct payers the live slots need before any distribution (0 disables)
+    pub min_unique_payers: u8,
+    /// Wallet that authorized each slot's funding transfer (parallel to `contributors`)
+    pub payers: Vec<Pubkey>,
+    /// Set by `freeze_all`: every state-changing instruction is refused
+    pub frozen: bool,
+    /// Mode of each completed distribution of the main vault, oldest first (last
+    /// `MAX_MODE_HISTORY`); `distribute_b` isn't recorded
+    pub mode_history: Vec<u8>,
+    /// `plan_hash` of the parameters `commit_plan` fixed, without the payouts: vault B
+    /// holds a different balance, so `distribute_b` can only be held to the same rule
+    pub committed_rule: Option<[u8; 32]>,
+    /// Unix time after which no new slot is funded (0 for none); see `extend_deadline`
+    pub deposit_deadline: i64,
+}
+
+/// ✅ Linear vesting curve for mode 19
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VestingSchedule {
+    pub start: i64,
+    pub cliff_secs: i64,
+    pub duration_secs: i64,
+}
+
+impl VestingSchedule {
+    /// ✅ How much of `allocation` has vested at `now`
+    pub fn vested(&self, allocation: u64, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.start);
+        if elapsed < self.cliff_secs {
+            return 0;
+        }
+        if elapsed >= self.duration_secs {
+            return allocation;
+        }
+        (allocation as u128 * elapsed as u128 / self.duration_secs as u128) as u64
+    }
+}
+
+/// ✅ A recipient's mode 19 allocation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VestingGrant {
+    pub recipient: Pubkey,
+    pub allocation: u64,
+    pub claimed: u64,
+}
+
+/// ✅ A share kept in the vault until `recipient` opts in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct HeldPayout {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+impl EscrowState {
+    /// ✅ Whether this program reads and writes the escrow's layout version
+    pub fn is_supported_version(&self) -> bool {
+        (MIN_ESCROW_VERSION..=ESCROW_VERSION).contains(&self.version)
+    }
+
+    /// Account size allocated by `initialize` and `initialize_from_template`
+    #[rustfmt::skip]
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 4 + (5 * 32) + 1 + 4 + 32 + (1 + 32) + 8 + 8 + (1 + 32) + 1 + 1 + 1 + (1 + 32) + 4 + (5 * 8) + 4 + (5 * 32) + (1 + 32) + 4 + 5 + (1 + 32) + 8 + 8 + 4 + (5 * 8) + 1 + 32 + 8 + (1 + 32) + 4 + 5 * (4 + 32) + 8 + 2 + 1 + 1 + 8 + (1 + 1) + 32 + 8 + 2 + 8 + 8 + 1 + 4 + (5 * 32) + (1 + 32) + 8 + (1 + 32) + 8 + 1 + 32 + 4 + (MAX_TAGS * 4) + 32 + 8 + 4 + (MAX_ACCEPTED_MINTS * 32) + 4 + (5 * 32) + 1 + 4 + (5 * 32) + 4 + 5 * (32 + 8) + (1 + 32) + 8 + 1 + 8 + 1 + 4 + (5 * 32) + 8 + (1 + 24) + 4 + 5 * (32 + 8 + 8) + 1 + 4 + (MAX_TARGET_WHITELIST * 32) + 1 + 4 + (5 * 32) + 1 + 4 + MAX_MODE_HISTORY + (1 + 32) + 8;
+
+    /// ✅ Whether `who` holds a funded slot
+    pub fn has_deposited(&self, who: &Pubkey) -> bool {
+        self.contributors
+            .iter()
+            .zip(&self.amounts)
+            .any(|(c, amount)| c == who && *amount > 0)
+    }
+
+    /// ✅ Whether the owner's `allowed_modes` permits distributing with `mode`
+    pub fn mode_allowed(&self, mode: u8) -> bool {
+        self.allowed_modes == 0 || mode_bit(mode) & self.allowed_modes != 0
+    }
+
+    /// ✅ Ready to distribute: all 5 slots funded, or `target_total` reached
+    pub fn is_full(&self) -> bool {
+        match self.fullness_mode {
+            FullnessMode::Count => {
+                self.contributors.len() == 5 && self.amounts.iter().all(|amount| *amount > 0)
+            }
+            FullnessMode::Total => {
+                !self.contributors.is_empty()
+                    && self.amounts.iter().all(|amount| *amount > 0)
+                    && self.total_deposited() >= self.target_total
+            }
+        }
+    }
+
+    /// ✅ Append a completed distribution's mode to `mode_history`
+    pub fn record_mode(&mut self, mode: u8) {
+        if self.mode_history.len() == MAX_MODE_HISTORY {
+            self.mode_history.remove(0);
+        }
+        self.mode_history.push(mode);
+    }
+
+    /// ✅ Sum of all funded slots
+    pub fn total_deposited(&self) -> u64 {
+        self.amounts.iter().sum()
+    }
+
+    /// ✅ Whether slots may be funded in `mint`
+    pub fn accepts_mint(&self, mint: &Pubkey) -> bool {
+        *mint == self.token_mint || self.accepted_mints.contains(mint)
+    }
+
+    /// ✅ Sum of the slots funded in `mint`
+    pub fn deposited_in(&self, mint: &Pubkey) -> u64 {
+        self.amounts
+            .iter()
+            .zip(&self.deposit_mints)
+            .filter(|(_, m)| *m == mint)
+            .map(|(amount, _)| amount)
+            .sum()
+    }
+}
+
+/// ✅ What makes a pool full
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FullnessMode {
+    /// All 5 contributor slots funded
+    Count,
+    /// Deposits add up to `target_total`, however many contributors that takes
+    Total,
+}
+
+/// ✅ Reusable `initialize` settings an operator keeps for similar escrows
+#[account]
+pub struct TemplateConfig {
+    pub operator: Pubkey,
+    pub fallback_sweep: Option<Pubkey>,
+    pub sweep_after_secs: i64,
+    pub owner_can_win: bool,
+    pub independent_owner: bool,
+    pub attester: Option<Pubkey>,
+    pub gate_mint: Option<Pubkey>,
+    pub gate_min_balance: u64,
+    pub required_deposit: u64,
+    pub close_on_distribute: bool,
+    pub burn_bps: u16,
+    pub fullness_mode: FullnessMode,
+    pub target_total: u64,
+    pub allowed_modes: u16,
+    pub auto_mode: Option<u8>,
+    pub auto_target: Pubkey,
+}
+
+/// ✅ How much of a claimant's Merkle allotment has been paid out
+#[account]
+pub struct ClaimRecord {
+    pub claimed_amount: u64,
+}
+
+/// ✅ An attester's statement that `subject` may join gated escrows until `expires_at`
+#[account]
+pub struct Attestation {
+    pub attester: Pubkey,
+    pub subject: Pubkey,
+    pub expires_at: i64,
+    /// Slot the attester first saw `subject` active (0 when not vouched for)
+    pub first_seen_slot: u64,
+}
+
+/// ✅ A short human-friendly code standing for `referrer`
+#[account]
+pub struct ReferralCode {
+    pub referrer: Pubkey,
+    pub code: String,
+}
+
+/// ✅ Next nonce a token owner's deposit permit must sign, shared across escrows
+#[account]
+pub struct PermitNonce {
+    pub nonce: u64,
+}
+
+/// ✅ Left behind by `close_escrow` at a retired escrow address
+#[account]
+pub struct EscrowTombstone {
+    pub escrow: Pubkey,
+    pub closed_at: i64,
+    /// `initialize` refuses this address until then
+    pub reopens_at: i64,
+}
+
+/// ✅ How many of `owner`'s escrows are open, for `MAX_ESCROWS_PER_OWNER`
+#[account]
+pub struct OwnerRegistry {
+    pub owner: Pubkey,
+    pub escrow_count: u32,
+}
+
+/// ✅ Append-only record of distributions, kept apart from (prunable) logs
+#[account]
+pub struct AuditLog {
+    pub escrow: Pubkey,
+    pub records: Vec<AuditRecord>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AuditRecord {
+    pub mode: u8,
+    pub total: u64,
+    pub timestamp: i64,
+    pub recipient_count: u8,
+}
+
+/// ✅ Return value of `vault_info` (same encoding as `(u64, Pubkey)`)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultSnapshot {
+    pub amount: u64,
+    pub mint: Pubkey,
+}
+
+/// ✅ Return value of `distribution_status`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DistributionStatus {
+    pub distributed: bool,
+    pub distributed_at: i64,
+}
+
+/// ✅ Return value of `get_config` (at most 720 bytes, well under the 1024-byte return data limit)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EscrowConfig {
+    pub owner: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_mint_b: Option<Pubkey>,
+    pub required_deposit: u64,
+    pub fullness_mode: FullnessMode,
+    pub target_total: u64,
+    pub allowed_modes: u16,
+    pub auto_mode: Option<u8>,
+    pub auto_target: Pubkey,
+    pub burn_bps: u16,
+    pub owner_can_win: bool,
+    pub independent_owner: bool,
+    pub close_on_distribute: bool,
+    pub attester: Option<Pubkey>,
+    pub gate_mint: Option<Pubkey>,
+    pub gate_min_balance: u64,
+    pub merkle_root: [u8; 32],
+    pub fallback_sweep: Option<Pubkey>,
+    pub sweep_after_secs: i64,
+    pub distribute_fallback_secs: i64,
+    pub min_deposit_interval_slots: u64,
+    pub is_nft: bool,
+    pub price_oracle: Option<Pubkey>,
+    pub oracle_max_age_secs: i64,
+    pub tags: Vec<u32>,
+    pub min_account_age_slots: u64,
+    pub accepted_mints: Vec<Pubkey>,
+    pub opt_in_required: bool,
+    pub min_payout: u64,
+    pub dust_to_owner: bool,
+    pub reinit_cooldown_secs: i64,
+    pub owner_is_pda: bool,
+    pub target_whitelist: Vec<Pubkey>,
+    pub min_unique_payers: u8,
+    pub deposit_deadline: i64,
+}
+
+/// ✅ One entry of `batch_vault_info` (same encoding as `(Pubkey, u64)`)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultBalance {
+    pub escrow: Pubkey,
+    pub balance: u64,
+}
+
+/// ✅ Return value of `get_addresses` (same encoding as `(Pubkey, Pubkey, Pubkey)`)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EscrowAddresses {
+    pub escrow: Pubkey,
+    pub vault_auth: Pubkey,
+    pub vault_ata: Pubkey,
+}
+
+/// ✅ Return value of `reveal_and_distribute`: the winner, their slot index and the slot
+/// hash mixed into the draw
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LotteryWinner {
+    pub winner: Pubkey,
+    pub index: u8,
+    pub slot_hash: [u8; 32],
+}
+
+/// ✅ A contributor filled their slot with `amount`, optionally noting `memo`
+#[event]
+pub struct DepositMade {
+    pub escrow: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub memo: String,
+}
+
+/// ✅ A distribution burned `amount` of the escrow's mint
+#[event]
+pub struct Burned {
+    pub escrow: Pubkey,
+    pub amount: u64,
+}
+
+/// ✅ `eject_contributor` removed `contributor` and refunded `amount`
+#[event]
+pub struct Ejected {
+    pub escrow: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+}
+
+/// ✅ The escrow's category tags were set at `initialize` or changed by `set_tags`
+#[event]
+pub struct TagsSet {
+    pub escrow: Pubkey,
+    pub tags: Vec<u32>,
+}
+
+/// ✅ `transfer_slot` handed a contributor slot from `from` to `to`
+#[event]
+pub struct SlotTransferred {
+    pub escrow: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+/// ✅ `commit_plan` fixed the distribution's payouts to `hash`
+#[event]
+pub struct PlanCommitted {
+    pub escrow: Pubkey,
+    pub hash: [u8; 32],
+    pub committed_at: i64,
+}
+
+/// ✅ `extend_deadline` moved the deposit deadline from `old_deadline` to `new_deadline`
+#[event]
+pub struct DeadlineExtended {
+    pub escrow: Pubkey,
+    pub old_deadline: i64,
+    pub new_deadline: i64,
 }
 
 #[error_code]
@@ -250,7 +5455,7 @@ pub enum EscrowError {
     MaxContributorsReached,
     #[msg("Contributor already deposited")]
     AlreadyDeposited,
-    #[msg("Deposit must be exactly 5 tokens")]
+    #[msg("Deposit must cover the required slot amount")]
     InvalidDepositAmount,
     #[msg("Unauthorized")]
     Unauthorized,
@@ -268,4 +5473,221 @@ pub enum EscrowError {
     NameMismatch,
     #[msg("Target or excluded contributor is invalid")]
     InvalidTarget,
+    #[msg("Recipient ATA is not an initialized token account for the target and mint")]
+    InvalidRecipientAta,
+    #[msg("Fallback sweep window has not elapsed")]
+    SweepNotReady,
+    #[msg("No fallback sweep address configured")]
+    NoFallbackConfigured,
+    #[msg("Basis points must not exceed 10000")]
+    InvalidBps,
+    #[msg("Audit log is full")]
+    AuditLogFull,
+    #[msg("Mint is neither of the escrow's mints")]
+    UnknownMint,
+    #[msg("Second mint already configured")]
+    SecondMintAlreadySet,
+    #[msg("Not a contributor of this escrow")]
+    NotAContributor,
+    #[msg("Distribution shares do not reconcile with the vault balance")]
+    DistributionMathError,
+    #[msg("The escrow owner cannot be the winner")]
+    OwnerCannotWin,
+    #[msg("The escrow owner cannot deposit into an independent-owner escrow")]
+    OwnerCannotDeposit,
+    #[msg("Delegate's approved amount doesn't cover the deposit")]
+    InsufficientDelegation,
+    #[msg("Contributor has no valid attestation from the escrow's attester")]
+    NotAttested,
+    #[msg("Contributor is already registered")]
+    DuplicateContributor,
+    #[msg("Contributor has already deposited funds")]
+    ContributorHasFunds,
+    #[msg("Escrow account version is not supported by this program")]
+    UnsupportedVersion,
+    #[msg("Target escrow or its vault is invalid")]
+    InvalidTargetEscrow,
+    #[msg("Name must be non-empty printable ASCII")]
+    InvalidName,
+    #[msg("Cannot skip every contributor")]
+    CannotSkipAll,
+    #[msg("Revealed seed does not match the commitment")]
+    CommitMismatch,
+    #[msg("No winner commitment has been made")]
+    NotCommitted,
+    #[msg("Pool is already full")]
+    PoolAlreadyFull,
+    #[msg("Source token account must be the contributor's and hold the escrow mint")]
+    InvalidSourceAccount,
+    #[msg("Lead amount exceeds the vault balance")]
+    LeadAmountTooLarge,
+    #[msg("Contributor has already been refunded")]
+    AlreadyRefunded,
+    #[msg("Contributor does not hold enough of the gate token")]
+    GateNotSatisfied,
+    #[msg("Pool already has contributors")]
+    PoolAlreadyStarted,
+    #[msg("No contributors are eligible to receive a payout")]
+    NoEligibleRecipients,
+    #[msg("Recipient cannot be the escrow vault itself")]
+    SelfTransfer,
+    #[msg("Merkle proof does not match the escrow's root")]
+    InvalidProof,
+    #[msg("Allotment has already been claimed")]
+    AlreadyClaimed,
+    #[msg("Vault balance is too low to give every recipient a share")]
+    BalanceTooLowToSplit,
+    #[msg("Vault token account is frozen by the mint's freeze authority")]
+    VaultFrozen,
+    #[msg("No distribution target has been set")]
+    NoStoredTarget,
+    #[msg("Vault must be empty at initialization")]
+    VaultNotEmpty,
+    #[msg("Memo cannot exceed 32 bytes")]
+    MemoTooLong,
+    /// No longer returned: a plan pays at most the 5 slots, which always fits one call;
+    /// kept so later codes don't shift
+    #[msg("Too many recipients for a single call")]
+    TooManyRecipients,
+    /// No longer returned: funded slots always hold `required_deposit`, which can't
+    /// change once anyone has joined; kept so later codes don't shift
+    #[msg("Deposit already matches the required amount")]
+    NothingToReconcile,
+    /// No longer returned (see `EscrowState::locked`); kept so later codes don't shift
+    #[msg("Escrow is locked by a distribution in progress")]
+    Reentrancy,
+    #[msg("Deposit permit signature is missing or invalid")]
+    InvalidPermit,
+    #[msg("Total fullness needs a non-zero target total")]
+    InvalidTargetTotal,
+    #[msg("Auto-distribution can't be combined with burning, closing the vault, opt-in, dust to owner or a unique-payer minimum")]
+    AutoDistributeUnsupported,
+    #[msg("Escrow mint is not wrapped SOL")]
+    NotNativeMint,
+    #[msg("Temporary unwrap account must come first in remaining_accounts")]
+    InvalidUnwrapAccount,
+    #[msg("Missing recipient wallet in remaining_accounts")]
+    MissingRecipientWallet,
+    #[msg("Per-contributor vectors are out of sync")]
+    StateCorrupted,
+    #[msg("Contributor fallback distribution is not available yet")]
+    FallbackNotReady,
+    #[msg("Distribution mode is not allowed for this escrow")]
+    ModeNotAllowed,
+    #[msg("Too few slots since the previous deposit")]
+    DepositTooSoon,
+    #[msg("Swap delivered less than the minimum amount out")]
+    SlippageExceeded,
+    #[msg("Swap moved a different amount out of the vault than its share")]
+    SwapMismatch,
+    #[msg("Swap program must be an executable other than the token or escrow program")]
+    InvalidSwapProgram,
+    #[msg("The vault authority cannot be a contributor")]
+    InvalidContributor,
+    #[msg("Mint is not an NFT (0 decimals, supply 1)")]
+    NotAnNft,
+    #[msg("An NFT can't be split between recipients")]
+    CannotSplitNft,
+    #[msg("Escrow has not been distributed yet")]
+    NotDistributed,
+    #[msg("Contributor already acknowledged the distribution")]
+    AlreadyAcknowledged,
+    #[msg("Oracle price is older than the configured maximum age")]
+    StaleOracle,
+    #[msg("Price oracle is not configured, not the configured account, or unreadable")]
+    InvalidOracle,
+    #[msg("A batched distribution is in progress")]
+    DistributionInProgress,
+    #[msg("No batched distribution has been begun")]
+    NoPendingPlan,
+    #[msg("Batch parameters don't match the committed distribution plan")]
+    PlanMismatch,
+    #[msg("Recorded deposits don't match the vault balance")]
+    AccountingMismatch,
+    #[msg("Too many tags")]
+    TooManyTags,
+    #[msg("Vault holds less than the recorded deposits")]
+    VaultShortfall,
+    #[msg("Claim is empty or exceeds the unclaimed allotment")]
+    NothingToClaim,
+    #[msg("A minimum account age needs an attester to vouch for it")]
+    AgeCheckNeedsAttester,
+    #[msg("Contributor's wallet is younger than the escrow's minimum account age")]
+    AccountTooNew,
+    #[msg("Accepted mints must be distinct, exclude the escrow mint and stay within the limit")]
+    InvalidAcceptedMints,
+    #[msg("Vault is not the escrow's token account for this mint")]
+    InvalidVault,
+    #[msg("Pool holds deposits in more than one mint")]
+    MixedMintDistribution,
+    #[msg("None of the recipients has opted in")]
+    NoOptedInRecipients,
+    #[msg("Contributor already opted in")]
+    AlreadyOptedIn,
+    #[msg("Escrow requires opt-in; distribute it with distribute")]
+    OptInUnsupported,
+    #[msg("Payouts don't match the committed plan")]
+    PlanHashMismatch,
+    #[msg("A plan has already been committed")]
+    PlanAlreadyCommitted,
+    #[msg("Owner has reached the maximum number of open escrows")]
+    OwnerEscrowLimit,
+    #[msg("Escrow is counted in the owner registry, which must be passed")]
+    OwnerRegistryMissing,
+    #[msg("Vault can't pay every recipient the minimum payout")]
+    FloorUnaffordable,
+    #[msg("New slot owner is already a contributor")]
+    SlotTransferConflict,
+    #[msg("Owner's token account is needed to receive the dust")]
+    MissingOwnerAta,
+    #[msg("Distribution mode is newer than this program understands")]
+    UnknownMode,
+    #[msg("No referral code is registered under that name")]
+    UnknownReferralCode,
+    #[msg("Referral code must be 1-16 ASCII letters or digits")]
+    InvalidReferralCode,
+    #[msg("Mint account is not a usable token mint")]
+    InvalidMint,
+    #[msg("Ranking must list every contributor exactly once, with one share per rank")]
+    InvalidRanking,
+    #[msg("This escrow address was closed too recently to be initialized again")]
+    RecentlyClosed,
+    #[msg("Closing an escrow with a re-init cooldown needs its tombstone account")]
+    MissingTombstone,
+    #[msg("Pass up to 25 escrow and vault account pairs")]
+    InvalidBatchAccounts,
+    #[msg("Nothing has vested since the last claim")]
+    NothingVested,
+    #[msg("Vesting needs a positive duration and a cliff within it")]
+    InvalidVestingSchedule,
+    #[msg("A PDA owner must initialize through its program's CPI")]
+    OwnerNotPda,
+    #[msg("Mode 0 target is not on the escrow's target whitelist")]
+    TargetNotWhitelisted,
+    #[msg("Target whitelist may name at most 5 wallets")]
+    InvalidTargetWhitelist,
+    #[msg("Too few distinct wallets funded this pool to distribute it")]
+    InsufficientUniquePayers,
+    #[msg("Minimum unique payers can't exceed the 5 contributor slots")]
+    InvalidMinUniquePayers,
+    #[msg("Escrow is frozen")]
+    EscrowFrozen,
+    #[msg("Deposit permit has expired")]
+    PermitExpired,
+    #[msg("Held payouts or vesting grants are still unclaimed")]
+    UnclaimedPayouts,
+    #[msg("Claim exceeds the bonus set aside for claims")]
+    ClaimUnfunded,
+    #[msg("Winner commitment can't change once a deposit has landed")]
+    CommitmentLocked,
+    #[msg("SlotHashes sysvar holds no recent slot hash")]
+    NoSlotHash,
+    #[msg("Valuation overflowed")]
+    MathOverflow,
+    #[msg("Batch pays no recipients: count is 0 or the plan is already paid")]
+    EmptyBatch,
+    #[msg("Deposit deadline has passed")]
+    DepositDeadlinePassed,
+    #[msg("Escrow has no deposit deadline, or the new one isn't later")]
+    DeadlineNotExtended,
 }
@@ -1,15 +1,41 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { BonkEscrowFinal } from "../target/types/bonk_escrow_final";
+import { MockSwap } from "../target/types/mock_swap";
+import { MockMultisig } from "../target/types/mock_multisig";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
   createMint,
   getAssociatedTokenAddress,
   mintTo,
   createAccount,
+  closeAccount,
+  transfer,
+  getAccount,
+  getMint,
+  getOrCreateAssociatedTokenAccount,
+  approve,
+  freezeAccount,
+  thawAccount,
+  createWrappedNativeAccount,
+  NATIVE_MINT,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
 } from "@solana/spl-token";
-import { Keypair, SystemProgram, PublicKey } from "@solana/web3.js";
+import {
+  Keypair,
+  SystemProgram,
+  PublicKey,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  sendAndConfirmTransaction,
+  Transaction,
+} from "@solana/web3.js";
 import { assert } from "chai";
+import { keccak_256 } from "@noble/hashes/sha3";
 
 describe("bonk_escrow_final", () => {
   const provider = anchor.AnchorProvider.env();
@@ -17,6 +43,8 @@ describe("bonk_escrow_final", () => {
 
   const program = anchor.workspace.BonkEscrowFinal as Program<BonkEscrowFinal>;
   const owner = provider.wallet as anchor.Wallet;
+  const multisig = anchor.workspace.MockMultisig as Program<MockMultisig>;
+  const [multisigVault] = PublicKey.findProgramAddressSync([Buffer.from("vault")], multisig.programId);
 
   let mint: PublicKey;
   let vaultAta: PublicKey;
@@ -37,13 +65,238 @@ describe("bonk_escrow_final", () => {
     );
   }
 
+  // 🟢 Utility to derive the escrow PDA, vault authority and vault ATA for a name
+  async function deriveEscrow(name: string, tokenProgram = TOKEN_PROGRAM_ID, escrowOwner = owner.publicKey) {
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), escrowOwner.toBuffer(), Buffer.from(name)],
+      program.programId
+    );
+    const [vaultAuth] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault-auth"), escrow.toBuffer()],
+      program.programId
+    );
+    const vault = await getAssociatedTokenAddress(mint, vaultAuth, true, tokenProgram);
+    const [audit] = PublicKey.findProgramAddressSync(
+      [Buffer.from("audit"), escrow.toBuffer()],
+      program.programId
+    );
+    const [tombstone] = PublicKey.findProgramAddressSync(
+      [Buffer.from("tombstone"), escrow.toBuffer()],
+      program.programId
+    );
+    return { escrow, vaultAuth, vault, audit, tombstone };
+  }
+
+  // 🟢 Utility to initialize a fresh escrow; unset options fall back to defaults
+  async function initEscrow(
+    name: string,
+    opts: {
+      fallbackSweep?: PublicKey;
+      sweepAfterSecs?: number;
+      ownerCanWin?: boolean;
+      independentOwner?: boolean;
+      attester?: PublicKey;
+      gateMint?: PublicKey;
+      gateMinBalance?: number;
+      closeOnDistribute?: boolean;
+      merkleRoot?: number[];
+      burnBps?: number;
+      targetTotal?: number;
+      autoMode?: number;
+      autoTarget?: PublicKey;
+      distributeFallbackSecs?: number;
+      allowedModes?: number;
+      minDepositIntervalSlots?: number;
+      isNft?: boolean;
+      priceOracle?: PublicKey;
+      oracleMaxAgeSecs?: number;
+      tags?: number[];
+      minAccountAgeSlots?: number;
+      acceptedMints?: PublicKey[];
+      optInRequired?: boolean;
+      ownerRegistry?: PublicKey;
+      minPayout?: number;
+      dustToOwner?: boolean;
+      reinitCooldownSecs?: number;
+      ownerIsPda?: boolean;
+      multisigOwner?: boolean;
+      targetWhitelist?: PublicKey[];
+      minUniquePayers?: number;
+      depositDeadline?: number;
+      tokenProgram?: PublicKey;
+    } = {}
+  ) {
+    const tokenProgram = opts.tokenProgram ?? TOKEN_PROGRAM_ID;
+    const escrowOwner = opts.multisigOwner ? multisigVault : owner.publicKey;
+    const pdas = await deriveEscrow(name, tokenProgram, escrowOwner);
+    const builder = program.methods
+      .initialize(
+        name,
+        opts.fallbackSweep ?? null,
+        new anchor.BN(opts.sweepAfterSecs ?? 0),
+        opts.ownerCanWin ?? true,
+        opts.independentOwner ?? false,
+        opts.attester ?? null,
+        opts.gateMint ?? null,
+        new anchor.BN(opts.gateMinBalance ?? 0),
+        opts.closeOnDistribute ?? false,
+        opts.merkleRoot ?? Array(32).fill(0),
+        opts.burnBps ?? 0,
+        opts.targetTotal ? { total: {} } : { count: {} },
+        new anchor.BN(opts.targetTotal ?? 0),
+        opts.autoMode ?? null,
+        opts.autoTarget ?? PublicKey.default,
+        new anchor.BN(opts.distributeFallbackSecs ?? 0),
+        opts.allowedModes ?? 0,
+        new anchor.BN(opts.minDepositIntervalSlots ?? 0),
+        opts.isNft ?? false,
+        opts.priceOracle ?? null,
+        new anchor.BN(opts.oracleMaxAgeSecs ?? 0),
+        opts.tags ?? [],
+        new anchor.BN(opts.minAccountAgeSlots ?? 0),
+        opts.acceptedMints ?? [],
+        opts.optInRequired ?? false,
+        new anchor.BN(opts.minPayout ?? 0),
+        opts.dustToOwner ?? false,
+        new anchor.BN(opts.reinitCooldownSecs ?? 0),
+        opts.ownerIsPda ?? false,
+        opts.targetWhitelist ?? [],
+        opts.minUniquePayers ?? 0,
+        new anchor.BN(opts.depositDeadline ?? 0)
+      )
+      .accounts({
+        escrow: pdas.escrow,
+        owner: escrowOwner,
+        mint,
+        vaultAuth: pdas.vaultAuth,
+        vaultAta: pdas.vault,
+        ownerRegistry: opts.ownerRegistry ?? null,
+        tombstone: pdas.tombstone,
+        systemProgram: SystemProgram.programId,
+        tokenProgram,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      });
+    if (opts.multisigOwner) {
+      await multisigExecute(await builder.instruction());
+    } else {
+      await builder.rpc();
+    }
+    return pdas;
+  }
+
+  // 🟢 Utility running an escrow instruction through the mock multisig, its vault PDA signing
+  async function multisigExecute(ix: anchor.web3.TransactionInstruction) {
+    await multisig.methods
+      .execute(ix.data)
+      .accounts({ vault: multisigVault, targetProgram: ix.programId })
+      .remainingAccounts(ix.keys.map((k) => ({ ...k, isSigner: k.isSigner && !k.pubkey.equals(multisigVault) })))
+      .rpc();
+  }
+
+  // 🟢 Utility to deposit 5 tokens into `name` from each given contributor
+  async function depositAll(name: string, kps: Keypair[], atas: PublicKey[]) {
+    const pdas = await deriveEscrow(name);
+    for (let i = 0; i < kps.length; i++) {
+      await program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: kps[i].publicKey,
+          contributorAta: atas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([kps[i]])
+        .rpc();
+    }
+  }
+
+  // 🟢 Utility to distribute `name` with every contributor ATA in remaining accounts
+  async function distributeEscrow(
+    name: string,
+    mode: number,
+    target: PublicKey,
+    opts: {
+      winnerBps?: number;
+      leadAmount?: number;
+      remaining?: PublicKey[];
+      targetEscrow?: PublicKey;
+      targetVaultAta?: PublicKey;
+      ownerAta?: PublicKey;
+    } = {}
+  ) {
+    const pdas = await deriveEscrow(name);
+    const atas = opts.remaining ?? contributorAtas;
+    return program.methods
+      .distribute(name, mode, target, opts.winnerBps ?? 0, new anchor.BN(opts.leadAmount ?? 0))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: opts.targetEscrow ?? null,
+        targetVaultAta: opts.targetVaultAta ?? null,
+        ownerAta: opts.ownerAta ?? null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(atas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false })))
+      .rpc();
+  }
+
+  // 🟢 Utility minting `amount` to the owner and adding it to `name`'s bonus
+  async function topUp(name: string, amount: number) {
+    const pdas = await deriveEscrow(name);
+    const payer = (owner as any).payer;
+    const ownerAta = (await getOrCreateAssociatedTokenAccount(provider.connection, payer, mint, owner.publicKey))
+      .address;
+    await mintTo(provider.connection, payer, mint, ownerAta, owner.publicKey, BigInt(amount));
+    await program.methods
+      .ownerTopup(name, new anchor.BN(amount))
+      .accounts({
+        escrow: pdas.escrow,
+        owner: owner.publicKey,
+        ownerAta,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  // 🟢 Utility returning the token balance of each contributor ATA
+  async function balances(): Promise<bigint[]> {
+    return Promise.all(
+      contributorAtas.map(async (ata) => (await getAccount(provider.connection, ata)).amount)
+    );
+  }
+
+  // 🟢 Utility asserting that a transaction fails with the given Anchor error code
+  async function expectError(promise: Promise<unknown>, code: string) {
+    try {
+      await promise;
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, code);
+      return;
+    }
+    assert.fail(`Expected ${code} error`);
+  }
+
   it("🟢 Setup: Create Mint, ATAs, Contributors", async () => {
     // ✅ Create Mint
     mint = await createMint(
       provider.connection,
       (owner as any).payer, // owner payer
       owner.publicKey,
-      null,
+      owner.publicKey, // freeze authority, lets tests freeze a vault
       9
     );
 
@@ -88,20 +341,7 @@ describe("bonk_escrow_final", () => {
     vaultAta = await getAssociatedTokenAddress(mint, vaultAuthPda, true);
 
     // ✅ Initialize Escrow
-    await program.methods
-      .initialize(escrowName)
-      .accounts({
-        escrow: escrowPda,
-        owner: owner.publicKey,
-        mint,
-        vaultAuth: vaultAuthPda,
-        vaultAta,
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
-        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
-      })
-      .rpc();
+    await initEscrow(escrowName);
 
     console.log("✅ Escrow Initialized:", escrowPda.toBase58());
   });
@@ -109,13 +349,16 @@ describe("bonk_escrow_final", () => {
   it("🟢 Deposit: 5 tokens each from contributors", async () => {
     for (let i = 0; i < contributors.length; i++) {
       await program.methods
-        .deposit(escrowName, new anchor.BN(5)) // 5 tokens
+        .deposit(escrowName, new anchor.BN(5), false, "") // 5 tokens
         .accounts({
           escrow: escrowPda,
           contributor: contributors[i].publicKey,
           contributorAta: contributorAtas[i],
           vaultAta,
           vaultAuth: vaultAuthPda,
+          attestation: null,
+          gateAta: null,
+          mint,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .signers([contributors[i]])
@@ -135,13 +378,19 @@ describe("bonk_escrow_final", () => {
 
     
     await program.methods
-      .distribute(escrowName, 0, target)
+      .distribute(escrowName, 0, target, 0, new anchor.BN(0))
       .accounts({
         escrow: escrowPda,
         vaultAta,
         vaultAuth: vaultAuthPda,
         owner: owner.publicKey,
+        auditLog: (await deriveEscrow(escrowName)).audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .remainingAccounts(
          contributorAtas
@@ -167,20 +416,7 @@ describe("bonk_escrow_final", () => {
     vaultAta = await getAssociatedTokenAddress(mint, vaultAuthPda, true);
 
     // ✅ Init
-    await program.methods
-      .initialize(newName)
-      .accounts({
-        escrow: escrowPda,
-        owner: owner.publicKey,
-        mint,
-        vaultAuth: vaultAuthPda,
-        vaultAta,
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
-        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
-      })
-      .rpc();
+    await initEscrow(newName);
 
     console.log("✅ Second escrow initialized:", escrowPda.toBase58());
 
@@ -188,13 +424,16 @@ describe("bonk_escrow_final", () => {
     for (let i = 0; i < contributors.length; i++) {
   
       await program.methods
-        .deposit(newName, new anchor.BN(5))
+        .deposit(newName, new anchor.BN(5), false, "")
         .accounts({
           escrow: escrowPda,
           contributor: contributors[i].publicKey,
           contributorAta: contributorAtas[i],
           vaultAta,
           vaultAuth: vaultAuthPda,
+          attestation: null,
+          gateAta: null,
+          mint,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .signers([contributors[i]])
@@ -204,13 +443,19 @@ describe("bonk_escrow_final", () => {
     const excluded = contributors[4].publicKey;
 
     await program.methods
-      .distribute(newName, 1, excluded)
+      .distribute(newName, 1, excluded, 0, new anchor.BN(0))
       .accounts({
         escrow: escrowPda,
         vaultAta,
         vaultAuth: vaultAuthPda,
         owner: owner.publicKey,
+        auditLog: (await deriveEscrow(newName)).audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .remainingAccounts(
        contributorAtas
@@ -220,4 +465,3480 @@ describe("bonk_escrow_final", () => {
 
     console.log("✅ Mode 1 distribution done, excluded:", excluded.toBase58());
   });
+
+  it("🔴 Distribute Mode 0: Rejects an uninitialized account at the target ATA address", async () => {
+    const name = "escrow-no-ata";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    // ✅ Drain and close the target's ATA so only the address remains
+    const target = contributors[1];
+    const balance = (await getAccount(provider.connection, contributorAtas[1])).amount;
+    await transfer(
+      provider.connection,
+      (owner as any).payer,
+      contributorAtas[1],
+      contributorAtas[0],
+      target,
+      balance
+    );
+    await closeAccount(
+      provider.connection,
+      (owner as any).payer,
+      contributorAtas[1],
+      target.publicKey,
+      target
+    );
+
+    await expectError(
+      program.methods
+        .distribute(name, 0, target.publicKey, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .rpc(),
+      "InvalidRecipientAta"
+    );
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isFalse(state.distributed, "Escrow must stay undistributed");
+
+    // ✅ Restore the target's ATA for later tests
+    await createAccount(provider.connection, (owner as any).payer, mint, target.publicKey);
+    await mintTo(
+      provider.connection,
+      (owner as any).payer,
+      mint,
+      contributorAtas[1],
+      owner.publicKey,
+      10_000_000_000n
+    );
+  });
+
+  it("🟢 Sweep: Moves the vault to the fallback after the inactivity window", async () => {
+    const name = "escrow-sweep";
+    const treasury = Keypair.generate();
+    const treasuryAta = await createAccount(
+      provider.connection,
+      (owner as any).payer,
+      mint,
+      treasury.publicKey
+    );
+    const pdas = await initEscrow(name, { fallbackSweep: treasury.publicKey, sweepAfterSecs: 4 });
+    await depositAll(name, contributors.slice(0, 2), contributorAtas.slice(0, 2));
+
+    const sweep = (vaultAta = pdas.vault) =>
+      program.methods
+        .sweepToFallback(name)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta,
+          vaultAuth: pdas.vaultAuth,
+          fallbackAta: treasuryAta,
+          caller: contributors[3].publicKey,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[3]])
+        .rpc();
+
+    await expectError(sweep(), "SweepNotReady");
+
+    await new Promise((resolve) => setTimeout(resolve, 6000));
+
+    // ✅ Only the escrow's own vault is swept, not other accounts the vault authority controls
+    await approve(provider.connection, (owner as any).payer, contributorAtas[4], pdas.vaultAuth, contributors[4], 5n);
+    await expectError(sweep(contributorAtas[4]), "ConstraintTokenOwner");
+
+    await sweep();
+
+    const treasuryAccount = await getAccount(provider.connection, treasuryAta);
+    assert.equal(treasuryAccount.amount, 10n, "Fallback should receive the whole vault");
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.distributed);
+  });
+
+  it("🔴 Sweep: Rejects escrows without a fallback", async () => {
+    const name = "escrow-no-sweep";
+    const pdas = await initEscrow(name);
+
+    await expectError(
+      program.methods
+        .sweepToFallback(name)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          fallbackAta: contributorAtas[0],
+          caller: owner.publicKey,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc(),
+      "NoFallbackConfigured"
+    );
+  });
+
+  it("🟢 Deposit: Idempotent resubmit is a no-op", async () => {
+    const name = "escrow-retry";
+    const pdas = await initEscrow(name);
+
+    const submit = (idempotent: boolean, amount = 5) =>
+      program.methods
+        .deposit(name, new anchor.BN(amount), idempotent, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[0].publicKey,
+          contributorAta: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[0]])
+        .rpc();
+
+    await submit(true);
+    // ✅ Simulated RPC retry of the same deposit
+    await submit(true);
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+    const vault = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vault.amount, 5n, "Retry must not transfer again");
+
+    await expectError(submit(false), "AlreadyDeposited");
+    // ✅ A different amount is a new request, not a retry
+    await expectError(submit(true, 6), "AlreadyDeposited");
+  });
+
+  it("🟢 Distribute Mode 12: Winner share plus consolation split", async () => {
+    const name = "escrow-consolation";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    const before = await balances();
+    // ✅ 25 pooled: winner gets 60% (15), 10 split four ways (2 each), 2 dust to the winner
+    await distributeEscrow(name, 12, contributors[2].publicKey, { winnerBps: 6000 });
+    const after = await balances();
+
+    for (let i = 0; i < contributors.length; i++) {
+      const expected = i === 2 ? 17n : 2n;
+      assert.equal(after[i] - before[i], expected, `Contributor ${i} payout`);
+    }
+  });
+
+  it("🔴 Distribute Mode 12: Rejects winner_bps above 10000", async () => {
+    const name = "escrow-consolation-bps";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      distributeEscrow(name, 12, contributors[2].publicKey, { winnerBps: 10_001 }),
+      "InvalidBps"
+    );
+  });
+
+  it("🟢 Vault Info: Reports pooled balance and mint", async () => {
+    const name = "escrow-info";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 3), contributorAtas.slice(0, 3));
+
+    const info = await program.methods
+      .vaultInfo(name)
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAuth: pdas.vaultAuth,
+        vaultAta: pdas.vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .view();
+
+    assert.equal(info.amount.toNumber(), 15);
+    assert.isTrue(info.mint.equals(mint));
+  });
+
+  it("🔴 Distribute: Rejects a signer that isn't the escrow owner", async () => {
+    const name = "escrow-not-owner";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      program.methods
+        .distribute(name, 0, contributors[0].publicKey, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: contributors[0].publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .signers([contributors[0]])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("🟢 Audit Log: Distribution is recorded on-chain", async () => {
+    const name = "escrow-audit";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await distributeEscrow(name, 1, contributors[4].publicKey);
+
+    const log = await program.account.auditLog.fetch(pdas.audit);
+    assert.isTrue(log.escrow.equals(pdas.escrow));
+    assert.equal(log.records.length, 1);
+    assert.equal(log.records[0].mode, 1);
+    assert.equal(log.records[0].total.toNumber(), 25);
+    assert.equal(log.records[0].recipientCount, 4);
+    assert.isAbove(log.records[0].timestamp.toNumber(), 0);
+  });
+
+  it("🟢 Vault B: Second mint is pooled and distributed separately", async () => {
+    const name = "escrow-two-mints";
+    const pdas = await initEscrow(name);
+
+    const mintB = await createMint(
+      provider.connection,
+      (owner as any).payer,
+      owner.publicKey,
+      null,
+      6
+    );
+    const atasB: PublicKey[] = [];
+    for (const kp of contributors) {
+      const ata = await createAccount(provider.connection, (owner as any).payer, mintB, kp.publicKey);
+      await mintTo(provider.connection, (owner as any).payer, mintB, ata, owner.publicKey, 1_000n);
+      atasB.push(ata);
+    }
+    const vaultB = await getAssociatedTokenAddress(mintB, pdas.vaultAuth, true);
+
+    await program.methods
+      .initVaultB(name)
+      .accounts({
+        escrow: pdas.escrow,
+        owner: owner.publicKey,
+        mintB,
+        vaultAuth: pdas.vaultAuth,
+        vaultAtaB: vaultB,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      })
+      .rpc();
+
+    await depositAll(name, contributors, contributorAtas);
+    for (const i of [0, 3]) {
+      await program.methods
+        .depositB(name, new anchor.BN(7))
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          mintB,
+          contributorAtaB: atasB[i],
+          vaultAtaB: vaultB,
+          vaultAuth: pdas.vaultAuth,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+    }
+
+    // ✅ The owner commits to paying contributor 1; vault B is held to the same rule
+    await program.methods
+      .commitPlan(name, 0, contributors[1].publicKey, 0, new anchor.BN(0))
+      .accounts({
+        escrow: pdas.escrow,
+        owner: owner.publicKey,
+        vaultAuth: pdas.vaultAuth,
+        vaultAta: pdas.vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const distributeB = (mintArg: PublicKey, vaultArg: PublicKey, target = contributors[1].publicKey) =>
+      program.methods
+        .distributeB(name, 0, target, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          mintB: mintArg,
+          vaultAtaB: vaultArg,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          atasB.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+
+    // ✅ Vault A's mint isn't the configured second mint
+    await expectError(distributeB(mint, pdas.vault), "UnknownMint");
+    await expectError(distributeB(mintB, vaultB, contributors[2].publicKey), "PlanHashMismatch");
+
+    await distributeB(mintB, vaultB);
+    const winner = await getAccount(provider.connection, atasB[1]);
+    assert.equal(winner.amount, 1_014n, "Winner receives all of vault B");
+
+    // ✅ Vault A is untouched and still distributable
+    const vaultA = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vaultA.amount, 25n);
+    assert.equal((await program.account.escrowState.fetch(pdas.escrow)).modeHistory.length, 0, "Vault B isn't in the history");
+    await distributeEscrow(name, 0, contributors[1].publicKey);
+  });
+
+  it("🟢 Distribute Mode 1: Payouts reconcile with the vault for various recipient counts", async () => {
+    // ✅ Excluding a contributor leaves 4 recipients (6 each, 1 left); an outsider leaves 5 (5 each)
+    const cases = [
+      { name: "escrow-math-4", excluded: contributors[0].publicKey, share: 6n, left: 1n },
+      { name: "escrow-math-5", excluded: Keypair.generate().publicKey, share: 5n, left: 0n },
+    ];
+
+    for (const c of cases) {
+      const pdas = await initEscrow(c.name);
+      await depositAll(c.name, contributors, contributorAtas);
+
+      const before = await balances();
+      await distributeEscrow(c.name, 1, c.excluded);
+      const after = await balances();
+
+      contributors.forEach((kp, i) => {
+        const expected = kp.publicKey.equals(c.excluded) ? 0n : c.share;
+        assert.equal(after[i] - before[i], expected, `${c.name}: contributor ${i}`);
+      });
+      const vault = await getAccount(provider.connection, pdas.vault);
+      assert.equal(vault.amount, c.left, `${c.name}: remainder left in vault`);
+    }
+  });
+
+  describe("Owner participation flags", () => {
+    let ownerAta: PublicKey;
+
+    // ✅ Owner plus the first four contributors fill the pool
+    const depositWithOwner = async (name: string) => {
+      const pdas = await deriveEscrow(name);
+      await program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: owner.publicKey,
+          contributorAta: ownerAta,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await depositAll(name, contributors.slice(0, 4), contributorAtas.slice(0, 4));
+    };
+
+    before(async () => {
+      ownerAta = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          (owner as any).payer,
+          mint,
+          owner.publicKey
+        )
+      ).address;
+      await mintTo(provider.connection, (owner as any).payer, mint, ownerAta, owner.publicKey, 1_000n);
+    });
+
+    it("🟢 owner_can_win: Owner deposits and wins", async () => {
+      const name = "escrow-owner-wins";
+      await initEscrow(name, { ownerCanWin: true, independentOwner: false });
+      await depositWithOwner(name);
+
+      await distributeEscrow(name, 0, owner.publicKey, {
+        remaining: [ownerAta, ...contributorAtas.slice(0, 4)],
+      });
+    });
+
+    it("🔴 !owner_can_win: Owner deposits but can't be the winner", async () => {
+      const name = "escrow-owner-no-win";
+      await initEscrow(name, { ownerCanWin: false, independentOwner: false });
+      await depositWithOwner(name);
+
+      const remaining = [ownerAta, ...contributorAtas.slice(0, 4)];
+      await expectError(
+        distributeEscrow(name, 0, owner.publicKey, { remaining }),
+        "OwnerCannotWin"
+      );
+      await distributeEscrow(name, 0, contributors[0].publicKey, { remaining });
+    });
+
+    for (const ownerCanWin of [true, false]) {
+      it(`🔴 independent_owner (owner_can_win=${ownerCanWin}): Owner can't deposit or be targeted`, async () => {
+        const name = `escrow-indep-${ownerCanWin}`;
+        const pdas = await initEscrow(name, { ownerCanWin, independentOwner: true });
+
+        await expectError(
+          program.methods
+            .deposit(name, new anchor.BN(5), false, "")
+            .accounts({
+              escrow: pdas.escrow,
+              contributor: owner.publicKey,
+              contributorAta: ownerAta,
+              vaultAta: pdas.vault,
+              vaultAuth: pdas.vaultAuth,
+              attestation: null,
+              gateAta: null,
+              mint,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .rpc(),
+          "OwnerCannotDeposit"
+        );
+
+        await depositAll(name, contributors, contributorAtas);
+        await expectError(distributeEscrow(name, 1, owner.publicKey), "OwnerCannotWin");
+        await distributeEscrow(name, 1, contributors[4].publicKey);
+      });
+    }
+  });
+
+  it("🟢 Deposit Delegated: Delegate deposits for the token owner", async () => {
+    const name = "escrow-delegated";
+    const pdas = await initEscrow(name);
+    const delegate = Keypair.generate();
+    await airdrop(delegate.publicKey);
+
+    const depositViaDelegate = () =>
+      program.methods
+        .depositDelegated(name, new anchor.BN(5))
+        .accounts({
+          escrow: pdas.escrow,
+          delegate: delegate.publicKey,
+          contributorAta: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([delegate])
+        .rpc();
+
+    // ✅ An approval below the deposit amount isn't enough
+    await approve(
+      provider.connection,
+      (owner as any).payer,
+      contributorAtas[0],
+      delegate.publicKey,
+      contributors[0],
+      4n
+    );
+    await expectError(depositViaDelegate(), "InsufficientDelegation");
+
+    await approve(
+      provider.connection,
+      (owner as any).payer,
+      contributorAtas[0],
+      delegate.publicKey,
+      contributors[0],
+      5n
+    );
+    await depositViaDelegate();
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+    assert.isTrue(state.contributors[0].equals(contributors[0].publicKey));
+    const vault = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vault.amount, 5n);
+  });
+
+  it("🟢 Attestation: Gated escrow only admits attested contributors", async () => {
+    const name = "escrow-kyc";
+    const attester = Keypair.generate();
+    await airdrop(attester.publicKey);
+    const pdas = await initEscrow(name, { attester: attester.publicKey });
+
+    const attestationFor = (subject: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("attestation"), attester.publicKey.toBuffer(), subject.toBuffer()],
+        program.programId
+      )[0];
+    const depositFrom = (i: number, attestation: PublicKey | null) =>
+      program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .attest(contributors[0].publicKey, new anchor.BN(now + 3600), new anchor.BN(0))
+      .accounts({
+        attestation: attestationFor(contributors[0].publicKey),
+        attester: attester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([attester])
+      .rpc();
+
+    // ✅ No attestation passed at all
+    await expectError(depositFrom(1, null), "NotAttested");
+    // ✅ Someone else's attestation doesn't count
+    await expectError(depositFrom(1, attestationFor(contributors[0].publicKey)), "NotAttested");
+
+    await depositFrom(0, attestationFor(contributors[0].publicKey));
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+  });
+
+  it("🟢 Validate Recipients: Returned ATAs are exactly what distribute needs", async () => {
+    const name = "escrow-validate";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    const excluded = contributors[4].publicKey;
+    const atas: PublicKey[] = await program.methods
+      .validateRecipients(name, 1, excluded, 0, new anchor.BN(0))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAuth: pdas.vaultAuth,
+        vaultAta: pdas.vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .view();
+
+    const expected = contributorAtas.slice(0, 4).map((a) => a.toBase58()).sort();
+    assert.deepEqual(atas.map((a) => a.toBase58()).sort(), expected);
+
+    // ✅ Passing exactly the returned set is enough for the real distribution
+    await distributeEscrow(name, 1, excluded, { remaining: atas });
+  });
+
+  it("🟢 Deposit: Final overshooting deposit only pulls what fills the pool", async () => {
+    const name = "escrow-overshoot";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 4), contributorAtas.slice(0, 4));
+
+    const before = await balances();
+    await program.methods
+      .deposit(name, new anchor.BN(9), false, "")
+      .accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[4].publicKey,
+        contributorAta: contributorAtas[4],
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        attestation: null,
+        gateAta: null,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([contributors[4]])
+      .rpc();
+    const after = await balances();
+
+    assert.equal(before[4] - after[4], 5n, "Surplus of 4 stays with the contributor");
+    const vault = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vault.amount, 25n, "Pool holds exactly its target");
+  });
+
+  it("🟢 Import/Remove: Owner corrects imported slots before they're funded", async () => {
+    const name = "escrow-import";
+    const pdas = await initEscrow(name);
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+
+    const typo = Keypair.generate().publicKey;
+    await program.methods
+      .importContributors(name, [contributors[0].publicKey, typo])
+      .accounts(ownerUpdate)
+      .rpc();
+    await program.methods.removeContributor(name, typo).accounts(ownerUpdate).rpc();
+
+    let state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+    assert.equal(state.amounts.length, 1, "Parallel vectors stay in sync");
+    assert.isTrue(state.contributors[0].equals(contributors[0].publicKey));
+    assert.equal(state.amounts[0].toNumber(), 0);
+
+    // ✅ Funding the imported slot locks it in
+    await depositAll(name, contributors.slice(0, 1), contributorAtas.slice(0, 1));
+    state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+    assert.equal(state.amounts[0].toNumber(), 5);
+
+    await expectError(
+      program.methods.removeContributor(name, contributors[0].publicKey).accounts(ownerUpdate).rpc(),
+      "ContributorHasFunds"
+    );
+  });
+
+  it("🔴 Version: Instructions reject an escrow with an unsupported layout version", async function () {
+    // ✅ Fixture loaded by the local test validator (see Anchor.toml)
+    const fixture = new PublicKey("68i654cpmdUkzhbSfhiVqsUxJvjdr2rh2bneRo2BGmqM");
+    if (!(await provider.connection.getAccountInfo(fixture))) {
+      this.skip();
+    }
+
+    await expectError(
+      program.methods
+        .removeContributor("escrow-v-next", contributors[0].publicKey)
+        .accounts({ escrow: fixture, owner: owner.publicKey })
+        .rpc(),
+      "UnsupportedVersion"
+    );
+  });
+
+  it("🟢 Distribute Mode 0: Feeds another escrow's vault (pool of pools)", async () => {
+    const inner = "escrow-inner";
+    const outer = "escrow-outer";
+    await initEscrow(inner);
+    const outerPdas = await initEscrow(outer);
+    await depositAll(inner, contributors, contributorAtas);
+    await depositAll(outer, contributors.slice(0, 2), contributorAtas.slice(0, 2));
+
+    // ✅ Target address must match the escrow account passed in
+    await expectError(
+      distributeEscrow(inner, 0, contributors[0].publicKey, {
+        targetEscrow: outerPdas.escrow,
+        targetVaultAta: outerPdas.vault,
+        remaining: [],
+      }),
+      "InvalidTargetEscrow"
+    );
+
+    await distributeEscrow(inner, 0, outerPdas.escrow, {
+      targetEscrow: outerPdas.escrow,
+      targetVaultAta: outerPdas.vault,
+      remaining: [],
+    });
+
+    const outerVault = await getAccount(provider.connection, outerPdas.vault);
+    assert.equal(outerVault.amount, 35n, "Outer vault holds its deposits plus the inner pool");
+  });
+
+  it("🔴 Initialize: Rejects empty, whitespace-only and control-character names", async () => {
+    for (const name of ["", "   ", "bad\tname", "bell\u0007"]) {
+      await expectError(initEscrow(name), "InvalidName");
+    }
+  });
+
+  it("🟢 Force Distribute: Skips listed contributors and records them", async () => {
+    const name = "escrow-force-skip";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    const forceSkip = (skip: PublicKey[]) =>
+      program.methods
+        .forceDistributeSkipping(name, skip)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          contributorAtas
+            .slice(0, 3)
+            .map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+
+    await expectError(forceSkip(contributors.map((kp) => kp.publicKey)), "CannotSkipAll");
+
+    // ✅ Only the first three receive; 25 / 3 = 8 each
+    const skipped = [contributors[3].publicKey, contributors[4].publicKey];
+    const before = await balances();
+    await forceSkip(skipped);
+    const after = await balances();
+
+    [8n, 8n, 8n, 0n, 0n].forEach((expected, i) =>
+      assert.equal(after[i] - before[i], expected, `Contributor ${i}`)
+    );
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.sameMembers(
+      state.skipped.map((k) => k.toBase58()),
+      skipped.map((k) => k.toBase58())
+    );
+  });
+
+  it("🟢 Commit-Reveal: Correct seed picks and pays the winner, wrong seed fails", async () => {
+    const name = "escrow-commit-reveal";
+    const pdas = await initEscrow(name);
+
+    const seed = Buffer.from(anchor.web3.Keypair.generate().secretKey.slice(0, 32));
+    const commit = (s: Buffer) =>
+      program.methods
+        .commitWinner(name, Array.from(keccak_256(s)))
+        .accounts({ escrow: pdas.escrow, owner: owner.publicKey })
+        .rpc();
+    await commit(seed);
+    await depositAll(name, contributors.slice(0, 1), contributorAtas.slice(0, 1));
+
+    // ✅ The commitment is locked once the first deposit lands
+    await expectError(commit(Buffer.alloc(32, 1)), "CommitmentLocked");
+    await depositAll(name, contributors.slice(1), contributorAtas.slice(1));
+
+    const reveal = (s: Buffer) =>
+      program.methods
+        .revealAndDistribute(name, Array.from(s))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+
+    const wrong = Buffer.from(seed);
+    wrong[31] ^= 0xff;
+    await expectError(reveal(wrong), "CommitMismatch");
+
+    // ✅ The slot hash at reveal time decides which contributor the seed lands on
+    const before = await balances();
+    await reveal(seed);
+    const after = await balances();
+    const gains = after.map((a, i) => a - before[i]);
+    assert.equal(gains.filter((g) => g === 25n).length, 1, "One winner takes the vault");
+    assert.equal(gains.filter((g) => g === 0n).length, 4);
+  });
+
+  it("🔴 Commit-Reveal: Reveal without a commitment fails", async () => {
+    const name = "escrow-no-commit";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      program.methods
+        .revealAndDistribute(name, Array(32).fill(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc(),
+      "NotCommitted"
+    );
+  });
+
+  it("🟢 Deposit: Accepts a non-ATA token account owned by the contributor", async () => {
+    const name = "escrow-non-ata";
+    const pdas = await initEscrow(name);
+
+    const auxAccount = await createAccount(
+      provider.connection,
+      (owner as any).payer,
+      mint,
+      contributors[2].publicKey,
+      Keypair.generate()
+    );
+    await mintTo(provider.connection, (owner as any).payer, mint, auxAccount, owner.publicKey, 5n);
+
+    await program.methods
+      .deposit(name, new anchor.BN(5), false, "")
+      .accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[2].publicKey,
+        contributorAta: auxAccount,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        attestation: null,
+        gateAta: null,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([contributors[2]])
+      .rpc();
+
+    const source = await getAccount(provider.connection, auxAccount);
+    assert.equal(source.amount, 0n);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.contributors[0].equals(contributors[2].publicKey));
+  });
+
+  it("🟢 Distribute Mode 13: Lead amount plus an even split of the rest", async () => {
+    const name = "escrow-lead";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      distributeEscrow(name, 13, contributors[3].publicKey, { leadAmount: 26 }),
+      "LeadAmountTooLarge"
+    );
+
+    // ✅ 25 pooled: lead gets 9, the other four split 16 (4 each)
+    const before = await balances();
+    await distributeEscrow(name, 13, contributors[3].publicKey, { leadAmount: 9 });
+    const after = await balances();
+
+    contributors.forEach((_, i) =>
+      assert.equal(after[i] - before[i], i === 3 ? 9n : 4n, `Contributor ${i}`)
+    );
+  });
+
+  it("🔴 Refund: A contributor can't be refunded twice", async () => {
+    const name = "escrow-refund-once";
+    const pdas = await initEscrow(name);
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+
+    const refund = () =>
+      program.methods
+        .refund(name, false)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[0].publicKey,
+          contributorAta: contributorAtas[0],
+          refundDestination: null,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[0]])
+        .rpc();
+
+    const before = await balances();
+    await refund();
+    const after = await balances();
+    assert.equal(after[0] - before[0], 5n);
+
+    await expectError(refund(), "AlreadyRefunded");
+    await expectError(depositAll(name, [contributors[0]], [contributorAtas[0]]), "AlreadyRefunded");
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.deepEqual(state.refunded, [true]);
+  });
+
+  it("🟢 Token Gate: Deposits require a minimum gate token balance", async () => {
+    const name = "escrow-gated";
+    const gateMint = await createMint(
+      provider.connection,
+      (owner as any).payer,
+      owner.publicKey,
+      null,
+      0
+    );
+    const pdas = await initEscrow(name, { gateMint, gateMinBalance: 2 });
+
+    const gateAtas: PublicKey[] = [];
+    for (const [i, held] of [2n, 1n].entries()) {
+      const gateAta = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        (owner as any).payer,
+        gateMint,
+        contributors[i].publicKey
+      );
+      await mintTo(provider.connection, (owner as any).payer, gateMint, gateAta.address, owner.publicKey, held);
+      gateAtas.push(gateAta.address);
+    }
+
+    const depositFrom = (i: number, gateAta: PublicKey | null) =>
+      program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    // ✅ No gate account, a balance below the minimum, or someone else's account all fail
+    await expectError(depositFrom(1, null), "GateNotSatisfied");
+    await expectError(depositFrom(1, gateAtas[1]), "GateNotSatisfied");
+    await expectError(depositFrom(1, gateAtas[0]), "GateNotSatisfied");
+
+    await depositFrom(0, gateAtas[0]);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.contributors[0].equals(contributors[0].publicKey));
+  });
+
+  it("🟢 Required Deposit: Adjustable until the first deposit", async () => {
+    const name = "escrow-required-deposit";
+    const pdas = await initEscrow(name);
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+
+    await program.methods.setRequiredDeposit(name, new anchor.BN(7)).accounts(ownerUpdate).rpc();
+
+    // ✅ The old 5 token slot no longer covers the deposit
+    await expectError(depositAll(name, [contributors[0]], [contributorAtas[0]]), "InvalidDepositAmount");
+
+    const before = await balances();
+    await program.methods
+      .deposit(name, new anchor.BN(7), false, "")
+      .accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[0].publicKey,
+        contributorAta: contributorAtas[0],
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        attestation: null,
+        gateAta: null,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([contributors[0]])
+      .rpc();
+    const after = await balances();
+    assert.equal(before[0] - after[0], 7n);
+
+    await expectError(
+      program.methods.setRequiredDeposit(name, new anchor.BN(5)).accounts(ownerUpdate).rpc(),
+      "PoolAlreadyStarted"
+    );
+  });
+
+  it("🟢 Distribute Mode 14: Earlier depositors get a larger time-weighted share", async () => {
+    const name = "escrow-time-weighted";
+    const pdas = await initEscrow(name);
+
+    // ✅ Stagger the first two deposits so their time in the pool differs
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await depositAll(name, [contributors[1]], [contributorAtas[1]]);
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await depositAll(name, contributors.slice(2), contributorAtas.slice(2));
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    const depositedAt = state.depositedAt.map((t: anchor.BN) => t.toNumber());
+    assert.isBelow(depositedAt[0], depositedAt[1]);
+    assert.isBelow(depositedAt[1], depositedAt[2]);
+
+    const before = await balances();
+    await distributeEscrow(name, 14, contributors[0].publicKey);
+    const after = await balances();
+    const paid = contributors.map((_, i) => after[i] - before[i]);
+
+    assert.equal(paid.reduce((a, b) => a + b, 0n), 25n, "Whole vault is paid out");
+    assert.isTrue(paid[0] > paid[1], "Earliest depositor gets the most");
+    assert.isTrue(paid[1] >= paid[2]);
+  });
+
+  it("🔴 Distribute Mode 1: Excluding the sole contributor has no eligible recipients", async () => {
+    const name = "escrow-no-eligible";
+    const pdas = await initEscrow(name);
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+
+    // ✅ Simulated through the dry run, which doesn't require a full pool
+    try {
+      await program.methods
+        .validateRecipients(name, 1, contributors[0].publicKey, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAuth: pdas.vaultAuth,
+          vaultAta: pdas.vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .view();
+    } catch (err) {
+      const logs: string[] = err.simulationResponse?.logs ?? err.logs ?? [];
+      assert.isTrue(logs.some((l) => l.includes("NoEligibleRecipients")));
+      return;
+    }
+    assert.fail("Expected NoEligibleRecipients error");
+  });
+
+  it("🟢 Templates: Two escrows instantiated from one template share its settings", async () => {
+    const templateName = "weekly-pool";
+    const [template] = PublicKey.findProgramAddressSync(
+      [Buffer.from("template"), owner.publicKey.toBuffer(), Buffer.from(templateName)],
+      program.programId
+    );
+    const fallback = Keypair.generate().publicKey;
+
+    const equalSplitOnly = 1 << 15;
+    const createTemplate = (autoMode: number) =>
+      program.methods
+        .createTemplate(templateName, fallback, new anchor.BN(3600), false, false, null, null, new anchor.BN(0), new anchor.BN(8), false, 0, { count: {} }, new anchor.BN(0), equalSplitOnly, autoMode, PublicKey.default)
+        .accounts({
+          template,
+          operator: owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    // ✅ Mode settings are checked as `initialize` checks them
+    await expectError(createTemplate(1), "ModeNotAllowed");
+    await createTemplate(15);
+
+    for (const name of ["escrow-tpl-a", "escrow-tpl-b"]) {
+      const pdas = await deriveEscrow(name);
+      await program.methods
+        .initializeFromTemplate(name, templateName)
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          mint,
+          template,
+          vaultAuth: pdas.vaultAuth,
+          vaultAta: pdas.vault,
+          ownerRegistry: null,
+          tombstone: pdas.tombstone,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+      const state = await program.account.escrowState.fetch(pdas.escrow);
+      assert.equal(state.name, name);
+      assert.isTrue(state.fallbackSweep.equals(fallback));
+      assert.equal(state.sweepAfterSecs.toNumber(), 3600);
+      assert.isFalse(state.ownerCanWin);
+      assert.equal(state.requiredDeposit.toNumber(), 8);
+      assert.equal(state.allowedModes, equalSplitOnly);
+      assert.equal(state.autoMode, 15);
+      assert.equal(state.contributors.length, 0);
+    }
+  });
+
+  it("🔴 Distribute: Rejects sending the vault to itself", async () => {
+    const name = "escrow-self-transfer";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      distributeEscrow(name, 0, pdas.escrow, {
+        targetEscrow: pdas.escrow,
+        targetVaultAta: pdas.vault,
+      }),
+      "SelfTransfer"
+    );
+  });
+
+  it("🟢 Close On Distribute: Vault is closed only when the payout empties it", async () => {
+    const even = await initEscrow("escrow-autoclose", { closeOnDistribute: true });
+    await depositAll("escrow-autoclose", contributors, contributorAtas);
+    await distributeEscrow("escrow-autoclose", 0, contributors[0].publicKey);
+    assert.isNull(await provider.connection.getAccountInfo(even.vault), "Empty vault is closed");
+
+    // ✅ 25 over 4 recipients leaves 1 token of dust behind
+    const dusty = await initEscrow("escrow-autoclose-dust", { closeOnDistribute: true });
+    await depositAll("escrow-autoclose-dust", contributors, contributorAtas);
+    await distributeEscrow("escrow-autoclose-dust", 1, contributors[0].publicKey);
+    const vault = await getAccount(provider.connection, dusty.vault);
+    assert.equal(vault.amount, 1n, "Vault with dust stays open");
+  });
+
+  it("🟢 Merkle Claims: Recipients claim their allotment once with a valid proof", async () => {
+    const name = "escrow-merkle";
+    const leaf = (who: PublicKey, amount: number) =>
+      Buffer.from(
+        keccak_256(Buffer.concat([who.toBuffer(), new anchor.BN(amount).toArrayLike(Buffer, "le", 8)]))
+      );
+    const hashPair = (a: Buffer, b: Buffer) =>
+      Buffer.from(keccak_256(Buffer.compare(a, b) <= 0 ? Buffer.concat([a, b]) : Buffer.concat([b, a])));
+
+    const leaves = [leaf(contributors[0].publicKey, 10), leaf(contributors[1].publicKey, 15)];
+    const root = hashPair(leaves[0], leaves[1]);
+
+    const pdas = await initEscrow(name, { merkleRoot: Array.from(root) });
+    await depositAll(name, contributors, contributorAtas);
+    await topUp(name, 25);
+
+    const claim = (i: number, amount: number, proof: Buffer[]) => {
+      const [claimRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("claim"), pdas.escrow.toBuffer(), contributors[i].publicKey.toBuffer()],
+        program.programId
+      );
+      return program.methods
+        .claimWithProof(name, new anchor.BN(amount), new anchor.BN(amount), proof.map((p) => Array.from(p)))
+        .accounts({
+          escrow: pdas.escrow,
+          claimant: contributors[i].publicKey,
+          claimRecord,
+          claimantAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([contributors[i]])
+        .rpc();
+    };
+
+    // ✅ Claiming more than the allotted amount doesn't match any leaf
+    await expectError(claim(1, 20, [leaves[0]]), "InvalidProof");
+
+    const before = await balances();
+    await claim(0, 10, [leaves[1]]);
+    await claim(1, 15, [leaves[0]]);
+    const after = await balances();
+    assert.equal(after[0] - before[0], 10n);
+    assert.equal(after[1] - before[1], 15n);
+
+    await expectError(claim(0, 10, [leaves[1]]), "AlreadyClaimed");
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.bonus.toNumber(), 0, "Claims draw the bonus down");
+  });
+
+  it("🟢 Owner Top-Up: Bonus grows the vault without taking a slot", async () => {
+    const name = "escrow-topup";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 2), contributorAtas.slice(0, 2));
+
+    const ownerAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        (owner as any).payer,
+        mint,
+        owner.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, (owner as any).payer, mint, ownerAta, owner.publicKey, 20n);
+
+    await program.methods
+      .ownerTopup(name, new anchor.BN(20))
+      .accounts({
+        escrow: pdas.escrow,
+        owner: owner.publicKey,
+        ownerAta,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 2, "Owner is not a contributor");
+    assert.equal(state.bonus.toNumber(), 20);
+    const vault = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vault.amount, 30n);
+  });
+
+  it("🔴 Merkle Claims: Claims beyond the owner's bonus fail with ClaimUnfunded", async () => {
+    const name = "escrow-claim-unfunded";
+    const leaf = Buffer.from(
+      keccak_256(
+        Buffer.concat([contributors[0].publicKey.toBuffer(), new anchor.BN(22).toArrayLike(Buffer, "le", 8)])
+      )
+    );
+    const pdas = await initEscrow(name, { merkleRoot: Array.from(leaf) });
+    await depositAll(name, contributors, contributorAtas);
+
+    const [claimRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("claim"), pdas.escrow.toBuffer(), contributors[0].publicKey.toBuffer()],
+      program.programId
+    );
+    const claim = (amount: number) =>
+      program.methods
+        .claimWithProof(name, new anchor.BN(22), new anchor.BN(amount), [])
+        .accounts({
+          escrow: pdas.escrow,
+          claimant: contributors[0].publicKey,
+          claimRecord,
+          claimantAta: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([contributors[0]])
+        .rpc();
+
+    // ✅ The 25 deposited tokens aren't claimable
+    await expectError(claim(22), "ClaimUnfunded");
+
+    await topUp(name, 5);
+    await expectError(claim(6), "ClaimUnfunded");
+    await claim(5);
+
+    const vault = await getAccount(provider.connection, pdas.vault);
+    assert.equal(vault.amount, 25n, "Every deposit is still backed");
+  });
+
+  it("🟢 Eject: Owner refunds and removes a mid-list contributor", async () => {
+    const name = "escrow-eject";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 3), contributorAtas.slice(0, 3));
+
+    const eject = (who: number) =>
+      program.methods
+        .ejectContributor(name, contributors[who].publicKey)
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          contributorAta: contributorAtas[who],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    let ejected: any = null;
+    const listener = program.addEventListener("ejected", (event) => (ejected = event));
+
+    const before = await balances();
+    await eject(1);
+    const after = await balances();
+    await program.removeEventListener(listener);
+
+    assert.equal(after[1] - before[1], 5n);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.deepEqual(
+      state.contributors.map((c: PublicKey) => c.toBase58()),
+      [contributors[0].publicKey.toBase58(), contributors[2].publicKey.toBase58()]
+    );
+    assert.equal(state.amounts.length, 2);
+    if (ejected) assert.isTrue(ejected.contributor.equals(contributors[1].publicKey));
+
+    await expectError(eject(1), "NotAContributor");
+  });
+
+  it("🟢 Commit-Reveal: Winner is reported through return data", async () => {
+    const name = "escrow-reveal-return";
+    const pdas = await initEscrow(name);
+
+    const seed = Buffer.from(anchor.web3.Keypair.generate().secretKey.slice(0, 32));
+    await program.methods
+      .commitWinner(name, Array.from(keccak_256(seed)))
+      .accounts({ escrow: pdas.escrow, owner: owner.publicKey })
+      .rpc();
+    await depositAll(name, contributors, contributorAtas);
+
+    const sig = await program.methods
+      .revealAndDistribute(name, Array.from(seed))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+      )
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const data = Buffer.from(tx.meta.returnData.data[0], "base64");
+    // ✅ LotteryWinner { winner: Pubkey, index: u8, slot_hash: [u8; 32] }
+    const reportedWinner = new PublicKey(data.subarray(0, 32));
+    const reportedIndex = data[32];
+    const slotHash = data.subarray(33, 65);
+
+    const draw = Buffer.from(keccak_256(Buffer.concat([seed, slotHash])));
+    const index = Number(draw.readBigUInt64LE(0) % 5n);
+    assert.equal(reportedIndex, index);
+    assert.isTrue(reportedWinner.equals(contributors[index].publicKey));
+  });
+
+  it("🔴 Frozen Vault: Deposit and distribute fail with VaultFrozen", async () => {
+    const name = "escrow-frozen";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 4), contributorAtas.slice(0, 4));
+
+    await freezeAccount(provider.connection, (owner as any).payer, pdas.vault, mint, owner.publicKey);
+    await expectError(depositAll(name, [contributors[4]], [contributorAtas[4]]), "VaultFrozen");
+
+    await thawAccount(provider.connection, (owner as any).payer, pdas.vault, mint, owner.publicKey);
+    await depositAll(name, [contributors[4]], [contributorAtas[4]]);
+
+    await freezeAccount(provider.connection, (owner as any).payer, pdas.vault, mint, owner.publicKey);
+    await expectError(distributeEscrow(name, 0, contributors[0].publicKey), "VaultFrozen");
+  });
+
+  it("🟢 Stored Target: Distribute pays the target recorded by set_target", async () => {
+    const name = "escrow-stored-target";
+    const pdas = await initEscrow(name);
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      program.methods.setTarget(name, Keypair.generate().publicKey).accounts(ownerUpdate).rpc(),
+      "NotAContributor"
+    );
+    // ✅ The owner changes their mind before executing
+    await program.methods.setTarget(name, contributors[1].publicKey).accounts(ownerUpdate).rpc();
+    await program.methods.setTarget(name, contributors[3].publicKey).accounts(ownerUpdate).rpc();
+
+    const before = await balances();
+    await program.methods
+      .distributeStored(name, 0, 0, new anchor.BN(0))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+      )
+      .rpc();
+    const after = await balances();
+
+    contributors.forEach((_, i) => assert.equal(after[i] - before[i], i === 3 ? 25n : 0n));
+  });
+
+  it("🟢 Initialize: Tolerates a pre-created empty vault ATA but not a funded one", async () => {
+    const precreate = async (name: string, funded: bigint) => {
+      const pdas = await deriveEscrow(name);
+      const ata = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        (owner as any).payer,
+        mint,
+        pdas.vaultAuth,
+        true
+      );
+      if (funded > 0n) {
+        await mintTo(provider.connection, (owner as any).payer, mint, ata.address, owner.publicKey, funded);
+      }
+      return pdas;
+    };
+
+    const pdas = await precreate("escrow-precreated", 0n);
+    await initEscrow("escrow-precreated");
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.name, "escrow-precreated");
+
+    await precreate("escrow-prefunded", 3n);
+    await expectError(initEscrow("escrow-prefunded"), "VaultNotEmpty");
+  });
+
+  it("🟢 Deposit Memo: Stored per slot, capped at 32 bytes", async () => {
+    const name = "escrow-memo";
+    const pdas = await initEscrow(name);
+
+    const depositWithMemo = (i: number, memo: string) =>
+      program.methods
+        .deposit(name, new anchor.BN(5), false, memo)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    await expectError(depositWithMemo(0, "x".repeat(33)), "MemoTooLong");
+    await depositWithMemo(0, "INV-2024-0042");
+    await depositWithMemo(1, "");
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.deepEqual(state.memos, ["INV-2024-0042", ""]);
+  });
+
+  it("🟢 Distribute Mode 15: Every contributor gets an equal share", async () => {
+    const name = "escrow-equal-all";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    const before = await balances();
+    await distributeEscrow(name, 15, contributors[2].publicKey);
+    const after = await balances();
+
+    contributors.forEach((_, i) => assert.equal(after[i] - before[i], 5n, `Contributor ${i}`));
+  });
+
+  it("🟢 Recipient Count: A payout to every slot fits in one call", async () => {
+    const name = "escrow-recipient-limit";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    // ✅ Mode 15 pays all 5 slots, the most any plan pays
+    const sig = await distributeEscrow(name, 15, contributors[0].publicKey);
+    assert.isString(sig);
+  });
+
+  it("🟢 Distribution Status: Reports whether and when the pool was paid out", async () => {
+    const name = "escrow-status";
+    const pdas = await initEscrow(name);
+    const status = () =>
+      program.methods.distributionStatus(name).accounts({ escrow: pdas.escrow }).view();
+
+    let current = await status();
+    assert.isFalse(current.distributed);
+    assert.equal(current.distributedAt.toNumber(), 0);
+
+    await depositAll(name, contributors, contributorAtas);
+    const start = Math.floor(Date.now() / 1000) - 60;
+    await distributeEscrow(name, 0, contributors[0].publicKey);
+
+    current = await status();
+    assert.isTrue(current.distributed);
+    assert.isAtLeast(current.distributedAt.toNumber(), start);
+  });
+
+  it("🟢 Burn: Distribution burns burn_bps of the vault before splitting", async () => {
+    const name = "escrow-burn";
+    await expectError(initEscrow(name, { burnBps: 5001 }), "InvalidBps");
+    await initEscrow(name, { burnBps: 2000 });
+    await depositAll(name, contributors, contributorAtas);
+
+    const supplyBefore = (await getMint(provider.connection, mint)).supply;
+    const before = await balances();
+    await distributeEscrow(name, 15, contributors[0].publicKey);
+    const after = await balances();
+    const supplyAfter = (await getMint(provider.connection, mint)).supply;
+
+    // ✅ 20% of 25 is burned, the remaining 20 is split 5 ways
+    assert.equal(supplyBefore - supplyAfter, 5n);
+    contributors.forEach((_, i) => assert.equal(after[i] - before[i], 4n, `Contributor ${i}`));
+  });
+
+  it("🟢 Get Addresses: Program-derived addresses match client derivation", async () => {
+    const name = "escrow-addresses";
+    const pdas = await initEscrow(name);
+
+    const addresses = await program.methods
+      .getAddresses(name)
+      .accounts({ escrow: pdas.escrow })
+      .view();
+
+    assert.isTrue(addresses.escrow.equals(pdas.escrow));
+    assert.isTrue(addresses.vaultAuth.equals(pdas.vaultAuth));
+    assert.isTrue(addresses.vaultAta.equals(pdas.vault));
+  });
+
+  it("🟢 Deposit With Permit: Relayer submits and pays for a signed deposit", async () => {
+    const name = "escrow-permit";
+    const pdas = await initEscrow(name);
+    const relayer = Keypair.generate();
+    await airdrop(relayer.publicKey);
+    const contributor = contributors[3];
+
+    // ✅ One-time approval of the vault authority, then everything else is off-chain
+    await approve(
+      provider.connection,
+      (owner as any).payer,
+      contributorAtas[3],
+      pdas.vaultAuth,
+      contributor,
+      5n
+    );
+
+    const [permitNonce] = PublicKey.findProgramAddressSync(
+      [Buffer.from("permit-nonce"), contributor.publicKey.toBuffer()],
+      program.programId
+    );
+    const farFuture = Math.floor(Date.now() / 1000) + 3600;
+    const permit = (amount: number, nonce = 0, expiresAt = farFuture) => {
+      const message = Buffer.concat([
+        pdas.escrow.toBuffer(),
+        new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+        new anchor.BN(nonce).toArrayLike(Buffer, "le", 8),
+        new anchor.BN(expiresAt).toArrayLike(Buffer, "le", 8),
+      ]);
+      const ix = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: contributor.secretKey,
+        message,
+      });
+      // ✅ Inline layout: 16-byte header, 32-byte public key, then the 64-byte signature
+      return { ix, sig: Array.from(ix.data.subarray(48, 112)), expiresAt };
+    };
+    const depositWithPermit = (amount: number, signed: ReturnType<typeof permit>) =>
+      program.methods
+        .depositWithPermit(name, new anchor.BN(amount), new anchor.BN(signed.expiresAt), signed.sig)
+        .accounts({
+          escrow: pdas.escrow,
+          relayer: relayer.publicKey,
+          contributorAta: contributorAtas[3],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          permitNonce,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([signed.ix])
+        .signers([relayer]);
+
+    // ✅ A permit for a different amount doesn't authorize this deposit
+    await expectError(depositWithPermit(5, permit(6)).rpc(), "InvalidPermit");
+    await expectError(depositWithPermit(5, permit(5, 0, 1)).rpc(), "PermitExpired");
+
+    const signed = permit(5);
+    const tx = await depositWithPermit(5, signed).transaction();
+    tx.feePayer = relayer.publicKey;
+    const relayerBefore = await provider.connection.getBalance(relayer.publicKey);
+    await sendAndConfirmTransaction(provider.connection, tx, [relayer]);
+    const relayerAfter = await provider.connection.getBalance(relayer.publicKey);
+
+    assert.isBelow(relayerAfter, relayerBefore, "Relayer paid the fee");
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.contributors[0].equals(contributor.publicKey));
+    assert.equal((await program.account.permitNonce.fetch(permitNonce)).nonce.toNumber(), 1);
+
+    // ✅ Ejected and re-approved, the contributor's used permit can't be replayed
+    await program.methods
+      .ejectContributor(name, contributor.publicKey)
+      .accounts({
+        escrow: pdas.escrow,
+        owner: owner.publicKey,
+        contributorAta: contributorAtas[3],
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    await approve(provider.connection, (owner as any).payer, contributorAtas[3], pdas.vaultAuth, contributor, 5n);
+    await expectError(depositWithPermit(5, signed).rpc(), "InvalidPermit");
+  });
+
+  it("🟢 Target Total: Pool fills once deposits reach the target, not the slot count", async () => {
+    const name = "escrow-target-total";
+    await initEscrow(name, { targetTotal: 10 });
+    await depositAll(name, contributors.slice(0, 1), contributorAtas.slice(0, 1));
+    await expectError(
+      distributeEscrow(name, 15, contributors[0].publicKey, { remaining: contributorAtas.slice(0, 1) }),
+      "NotFull"
+    );
+
+    await depositAll(name, contributors.slice(1, 2), contributorAtas.slice(1, 2));
+    // ✅ Three slots are still open, but the pool has met its target
+    await expectError(depositAll(name, contributors.slice(2, 3), contributorAtas.slice(2, 3)), "PoolAlreadyFull");
+
+    const before = await balances();
+    await distributeEscrow(name, 15, contributors[0].publicKey, { remaining: contributorAtas.slice(0, 2) });
+    const after = await balances();
+    await expectError(depositAll(name, contributors.slice(2, 3), contributorAtas.slice(2, 3)), "AlreadyDistributed");
+
+    // ✅ Two deposits of 5 meet the target of 10 and are split between the two depositors
+    assert.equal(after[0] - before[0], 5n);
+    assert.equal(after[1] - before[1], 5n);
+  });
+
+  it("🟢 Auto Distribute: The filling deposit distributes the pool in the same transaction", async () => {
+    const name = "escrow-auto";
+    await expectError(initEscrow(name, { autoMode: 15, burnBps: 100 }), "AutoDistributeUnsupported");
+    const pdas = await initEscrow(name, { autoMode: 15 });
+    await depositAll(name, contributors.slice(0, 4), contributorAtas.slice(0, 4));
+
+    const before = await balances();
+    await program.methods
+      .deposit(name, new anchor.BN(5), false, "")
+      .accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[4].publicKey,
+        contributorAta: contributorAtas[4],
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        attestation: null,
+        gateAta: null,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+      )
+      .signers([contributors[4]])
+      .rpc();
+    const after = await balances();
+
+    // ✅ 25 split 5 ways: the fifth depositor breaks even, the others get 5 back
+    contributors
+      .slice(0, 4)
+      .forEach((_, i) => assert.equal(after[i] - before[i], 5n, `Contributor ${i}`));
+    assert.equal(after[4], before[4]);
+    assert.isTrue((await program.account.escrowState.fetch(pdas.escrow)).distributed);
+  });
+
+  it("🟢 Distribute Native: wSOL pool pays recipients in native SOL", async () => {
+    const name = "escrow-native";
+    const tokenMint = mint;
+    mint = NATIVE_MINT; // ✅ The helpers derive the vault from `mint`
+    try {
+      const pdas = await initEscrow(name);
+      const wsolAtas: PublicKey[] = [];
+      for (const kp of contributors) {
+        wsolAtas.push(
+          await createWrappedNativeAccount(provider.connection, (owner as any).payer, kp.publicKey, 5)
+        );
+      }
+      await depositAll(name, contributors, wsolAtas);
+
+      const [unwrap] = PublicKey.findProgramAddressSync(
+        [Buffer.from("unwrap"), pdas.escrow.toBuffer()],
+        program.programId
+      );
+      const lamports = () =>
+        Promise.all(contributors.map((kp) => provider.connection.getBalance(kp.publicKey)));
+      const before = await lamports();
+      await program.methods
+        .distributeNative(name, 15, contributors[0].publicKey, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: unwrap, isWritable: true, isSigner: false },
+          ...contributors.map((kp) => ({ pubkey: kp.publicKey, isWritable: true, isSigner: false })),
+        ])
+        .rpc();
+      const after = await lamports();
+
+      // ✅ Each wallet receives its 5-lamport share as SOL; the temporary account is gone
+      contributors.forEach((_, i) => assert.equal(after[i] - before[i], 5, `Contributor ${i}`));
+      assert.isNull(await provider.connection.getAccountInfo(unwrap));
+      assert.equal(await provider.connection.getBalance(pdas.vaultAuth), 0);
+    } finally {
+      mint = tokenMint;
+    }
+  });
+
+  it("🔴 State Corrupted: Deposit rejects an escrow whose per-slot vectors are out of sync", async function () {
+    // ✅ Fixture mocks a wSOL escrow missing one `deposited_at` entry (see Anchor.toml)
+    const fixture = new PublicKey("ATULddvdChGdkEfXuLHKqKXBVKPSudL1uuWTTX4GxKC9");
+    if (!(await provider.connection.getAccountInfo(fixture))) {
+      this.skip();
+    }
+    const [vaultAuth] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault-auth"), fixture.toBuffer()],
+      program.programId
+    );
+    const wsolAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      (owner as any).payer,
+      NATIVE_MINT,
+      contributors[0].publicKey
+    );
+
+    await expectError(
+      program.methods
+        .deposit("escrow-misaligned", new anchor.BN(5), false, "")
+        .accounts({
+          escrow: fixture,
+          contributor: contributors[0].publicKey,
+          contributorAta: wsolAta.address,
+          vaultAta: contributorAtas[0],
+          vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[0]])
+        .rpc(),
+      "StateCorrupted"
+    );
+  });
+
+  it("🟢 Contributor Amount: Returns the recorded deposit for a contributor", async () => {
+    const name = "escrow-contributor-amount";
+    const pdas = await initEscrow(name);
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+    const amountOf = (who: PublicKey) =>
+      program.methods.contributorAmount(name, who).accounts({ escrow: pdas.escrow }).view();
+
+    assert.equal((await amountOf(contributors[0].publicKey)).toNumber(), 5);
+
+    try {
+      await amountOf(contributors[1].publicKey);
+    } catch (err) {
+      const logs: string[] = err.simulationResponse?.logs ?? err.logs ?? [];
+      assert.isTrue(logs.some((l) => l.includes("NotAContributor")));
+      return;
+    }
+    assert.fail("Expected NotAContributor error");
+  });
+
+  it("🟢 Fallback Distribute: A contributor splits a pool the owner left undistributed", async () => {
+    const name = "escrow-fallback-distribute";
+    const pdas = await initEscrow(name, { distributeFallbackSecs: 2 });
+    await depositAll(name, contributors, contributorAtas);
+    const fallback = () =>
+      program.methods
+        .fallbackDistribute(name)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          contributor: contributors[1].publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .signers([contributors[1]])
+        .rpc();
+
+    await expectError(fallback(), "FallbackNotReady");
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+
+    const before = await balances();
+    await fallback();
+    const after = await balances();
+
+    // ✅ Equal split regardless of any target the owner had in mind
+    contributors.forEach((_, i) => assert.equal(after[i] - before[i], 5n, `Contributor ${i}`));
+  });
+
+  it("🔴 Allowed Modes: Distribute rejects a mode outside the escrow's whitelist", async () => {
+    const name = "escrow-allowed-modes";
+    await initEscrow(name, { allowedModes: 0b11 }); // ✅ Modes 0 and 1 only
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(distributeEscrow(name, 4, contributors[0].publicKey), "ModeNotAllowed");
+
+    const before = await balances();
+    await distributeEscrow(name, 0, contributors[2].publicKey);
+    const after = await balances();
+    assert.equal(after[2] - before[2], 25n);
+  });
+
+  it("🔴 Allowed Modes: Commit-reveal and force-skip count as modes 0 and 1", async () => {
+    const name = "escrow-allowed-modes-paths";
+    const pdas = await initEscrow(name, { allowedModes: 1 << 15 }); // ✅ Equal split only
+    await depositAll(name, contributors, contributorAtas);
+    const accounts = {
+      escrow: pdas.escrow,
+      vaultAta: pdas.vault,
+      vaultAuth: pdas.vaultAuth,
+      owner: owner.publicKey,
+      auditLog: pdas.audit,
+      mint,
+      targetEscrow: null,
+      targetVaultAta: null,
+      ownerAta: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+
+    await expectError(
+      program.methods.revealAndDistribute(name, Array(32).fill(0)).accounts(accounts).rpc(),
+      "ModeNotAllowed"
+    );
+    await expectError(
+      program.methods.forceDistributeSkipping(name, [contributors[0].publicKey]).accounts(accounts).rpc(),
+      "ModeNotAllowed"
+    );
+  });
+
+  it("🟢 Dust Recipient: Earliest depositor (lowest pubkey on ties) gets the dust, not slot 0", async () => {
+    const name = "escrow-dust-order";
+    const pdas = await initEscrow(name);
+    // ✅ Deposit in reverse so storage order differs from contributor order
+    const order = [4, 3, 2, 1, 0];
+    await depositAll(
+      name,
+      order.map((i) => contributors[i]),
+      order.map((i) => contributorAtas[i])
+    );
+    await mintTo(provider.connection, (owner as any).payer, mint, pdas.vault, owner.publicKey, 2);
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    const slots = state.contributors.map((c, i) => ({ c, at: state.depositedAt[i].toNumber() }));
+    slots.sort((a, b) => a.at - b.at || Buffer.compare(a.c.toBuffer(), b.c.toBuffer()));
+    const expected = contributors.findIndex((kp) => kp.publicKey.equals(slots[0].c));
+
+    const before = await balances();
+    await distributeEscrow(name, 15, contributors[0].publicKey);
+    const after = await balances();
+
+    // ✅ 27 over 5: 5 each, the 2 left over go to the earliest depositor
+    contributors.forEach((_, i) =>
+      assert.equal(after[i] - before[i], i === expected ? 7n : 5n, `Contributor ${i}`)
+    );
+  });
+
+  it("🔴 Deposit Interval: Two deposits in the same slot are rejected", async () => {
+    const name = "escrow-deposit-interval";
+    const pdas = await initEscrow(name, { minDepositIntervalSlots: 1 });
+    const depositFrom = (i: number) =>
+      program.methods.deposit(name, new anchor.BN(5), false, "").accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[i].publicKey,
+        contributorAta: contributorAtas[i],
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        attestation: null,
+        gateAta: null,
+        mint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      });
+
+    // ✅ One transaction lands both deposits in the same slot
+    await expectError(
+      depositFrom(1)
+        .preInstructions([await depositFrom(0).instruction()])
+        .signers([contributors[0], contributors[1]])
+        .rpc(),
+      "DepositTooSoon"
+    );
+
+    // ✅ Spread over separate transactions they go through
+    await depositAll(name, contributors.slice(0, 2), contributorAtas.slice(0, 2));
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 2);
+    assert.isAbove(state.lastDepositSlot.toNumber(), 0);
+  });
+
+  it("🟢 Get Config: Returns every configured setting in one call", async () => {
+    const name = "escrow-config";
+    const pdas = await initEscrow(name, {
+      burnBps: 250,
+      targetTotal: 15,
+      allowedModes: 0b1000_0000_0000_0011,
+      distributeFallbackSecs: 600,
+      minDepositIntervalSlots: 3,
+      closeOnDistribute: true,
+    });
+
+    const config = await program.methods.getConfig(name).accounts({ escrow: pdas.escrow }).view();
+    assert.isTrue(config.owner.equals(owner.publicKey));
+    assert.isTrue(config.tokenMint.equals(mint));
+    assert.isNull(config.tokenMintB);
+    assert.equal(config.requiredDeposit.toNumber(), 5);
+    assert.deepEqual(config.fullnessMode, { total: {} });
+    assert.equal(config.targetTotal.toNumber(), 15);
+    assert.equal(config.allowedModes, 0b1000_0000_0000_0011);
+    assert.isNull(config.autoMode);
+    assert.equal(config.burnBps, 250);
+    assert.isTrue(config.closeOnDistribute);
+    assert.equal(config.distributeFallbackSecs.toNumber(), 600);
+    assert.equal(config.minDepositIntervalSlots.toNumber(), 3);
+  });
+
+  it("🟢 Distribute Swapped: Mode 16 pays each share out in another token through a swap", async () => {
+    const name = "escrow-swapped";
+    const swapProgram = anchor.workspace.MockSwap as Program<MockSwap>;
+    const payer = (owner as any).payer;
+
+    // ✅ Mock pool swapping the escrow mint for `outMint` at 1:2
+    const outMint = await createMint(provider.connection, payer, owner.publicKey, null, 9);
+    const [poolAuth] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool")],
+      swapProgram.programId
+    );
+    const poolIn = await getOrCreateAssociatedTokenAccount(provider.connection, payer, mint, poolAuth, true);
+    const poolOut = await getOrCreateAssociatedTokenAccount(provider.connection, payer, outMint, poolAuth, true);
+    await mintTo(provider.connection, payer, outMint, poolOut.address, owner.publicKey, 1_000);
+    const outAtas: PublicKey[] = [];
+    for (const kp of contributors) {
+      outAtas.push(
+        (await getOrCreateAssociatedTokenAccount(provider.connection, payer, outMint, kp.publicKey)).address
+      );
+    }
+
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+    const [swapAuth] = PublicKey.findProgramAddressSync(
+      [Buffer.from("swap-auth"), pdas.escrow.toBuffer()],
+      program.programId
+    );
+    const swapSource = await getAssociatedTokenAddress(mint, swapAuth, true);
+    const distributeSwapped = (minAmountOut: number) =>
+      program.methods
+        .distributeSwapped(name, new anchor.BN(minAmountOut))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          outputMint: outMint,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          swapAuth,
+          swapSource,
+          swapProgram: swapProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          ...outAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false })),
+          { pubkey: poolIn.address, isWritable: true, isSigner: false },
+          { pubkey: poolOut.address, isWritable: true, isSigner: false },
+          { pubkey: poolAuth, isWritable: false, isSigner: false },
+        ])
+        .rpc();
+
+    // ✅ A 5-token share only buys 10 out, short of an 11 minimum
+    await expectError(distributeSwapped(11), "SlippageExceeded");
+
+    await distributeSwapped(10);
+    for (const ata of outAtas) {
+      assert.equal((await getAccount(provider.connection, ata)).amount, 10n);
+    }
+    assert.equal((await getAccount(provider.connection, poolIn.address)).amount, 25n);
+    // ✅ Every share went through the scratch account, none of it stayed there
+    assert.equal((await getAccount(provider.connection, swapSource)).amount, 0n);
+  });
+
+  it("🔴 Invalid Contributor: The vault authority can't deposit", async () => {
+    const name = "escrow-vault-auth-contributor";
+    const pdas = await initEscrow(name);
+
+    // ✅ No key signs for the PDA, so the deposit never lands; on-chain it would
+    // fail with `InvalidContributor` instead of a cryptic transfer error
+    try {
+      await program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: pdas.vaultAuth,
+          contributorAta: pdas.vault,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      assert.fail("Deposit from the vault authority went through");
+    } catch (err) {
+      assert.notInclude(String(err), "went through");
+    }
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 0);
+  });
+
+  it("🟢 Refund Destination: Refund is paid to a separate account of the escrow mint", async () => {
+    const name = "escrow-refund-destination";
+    const payer = (owner as any).payer;
+    const pdas = await initEscrow(name);
+    await depositAll(name, [contributors[1]], [contributorAtas[1]]);
+
+    const wallet = Keypair.generate();
+    const destination = await createAccount(provider.connection, payer, mint, wallet.publicKey);
+    const otherMint = await createMint(provider.connection, payer, owner.publicKey, null, 9);
+    const wrongMint = await createAccount(provider.connection, payer, otherMint, wallet.publicKey);
+    const refundTo = (refundDestination: PublicKey) =>
+      program.methods
+        .refund(name, false)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[1].publicKey,
+          contributorAta: contributorAtas[1],
+          refundDestination,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[1]])
+        .rpc();
+
+    await expectError(refundTo(wrongMint), "InvalidRecipientAta");
+
+    const before = await balances();
+    await refundTo(destination);
+    const after = await balances();
+
+    assert.equal((await getAccount(provider.connection, destination)).amount, 5n);
+    assert.equal(after[1], before[1], "Deposit source is untouched");
+  });
+
+  it("🟢 NFT Pool: A single NFT is paid out whole and can't be split", async () => {
+    const name = "escrow-nft";
+    const payer = (owner as any).payer;
+    await expectError(initEscrow("escrow-not-nft", { isNft: true, targetTotal: 1 }), "NotAnNft");
+
+    const tokenMint = mint;
+    mint = await createMint(provider.connection, payer, owner.publicKey, null, 0);
+    try {
+      const nftAtas: PublicKey[] = [];
+      for (const kp of contributors) {
+        nftAtas.push(await createAccount(provider.connection, payer, mint, kp.publicKey));
+      }
+      await mintTo(provider.connection, payer, mint, nftAtas[0], owner.publicKey, 1);
+
+      await initEscrow(name, { isNft: true, targetTotal: 1 });
+      await depositAll(name, [contributors[0]], [nftAtas[0]]);
+
+      await expectError(
+        distributeEscrow(name, 15, contributors[0].publicKey, { remaining: nftAtas }),
+        "CannotSplitNft"
+      );
+      await distributeEscrow(name, 0, contributors[0].publicKey, { remaining: [nftAtas[0]] });
+      assert.equal((await getAccount(provider.connection, nftAtas[0])).amount, 1n);
+    } finally {
+      mint = tokenMint;
+    }
+  });
+
+  it("🟢 Simulate Recipient Amount: Reports one recipient's share for modes 0, 1 and 12", async () => {
+    const name = "escrow-simulate-recipient";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+    const simulate = (mode: number, target: PublicKey, recipient: PublicKey, winnerBps = 0) =>
+      program.methods
+        .simulateRecipientAmount(name, mode, target, winnerBps, new anchor.BN(0), recipient)
+        .accounts({ escrow: pdas.escrow, vaultAuth: pdas.vaultAuth, vaultAta: pdas.vault, tokenProgram: TOKEN_PROGRAM_ID })
+        .view();
+    const [a, b] = contributors.map((kp) => kp.publicKey);
+
+    // ✅ Mode 0: the target takes all 25, anyone else nothing
+    assert.equal((await simulate(0, a, a)).toNumber(), 25);
+    assert.equal((await simulate(0, a, b)).toNumber(), 0);
+    // ✅ Mode 1: the excluded contributor gets nothing, the other four 6 each
+    assert.equal((await simulate(1, a, a)).toNumber(), 0);
+    assert.equal((await simulate(1, a, b)).toNumber(), 6);
+    // ✅ Mode 12 at 60%: the winner takes 15 plus the consolation dust, the others 2 each
+    assert.equal((await simulate(12, a, a, 6_000)).toNumber(), 17);
+    assert.equal((await simulate(12, a, b, 6_000)).toNumber(), 2);
+  });
+
+  it("🟢 Acknowledge: Contributors sign off and the tally completes once all have", async () => {
+    const name = "escrow-acknowledge";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+    const acknowledge = (kp: Keypair) =>
+      program.methods
+        .acknowledge(name)
+        .accounts({ escrow: pdas.escrow, contributor: kp.publicKey })
+        .signers([kp])
+        .rpc();
+    const allAcknowledged = () =>
+      program.methods.allAcknowledged(name).accounts({ escrow: pdas.escrow }).view();
+
+    await expectError(acknowledge(contributors[0]), "NotDistributed");
+    await distributeEscrow(name, 15, contributors[0].publicKey);
+
+    // ✅ Partial: four of five signed off
+    for (const kp of contributors.slice(0, 4)) {
+      await acknowledge(kp);
+    }
+    await expectError(acknowledge(contributors[0]), "AlreadyAcknowledged");
+    assert.isFalse(await allAcknowledged());
+
+    // ✅ Full: the last one completes the trail
+    await acknowledge(contributors[4]);
+    assert.isTrue(await allAcknowledged());
+  });
+
+  it("🟢 Vault USD Value: Values the vault through the price oracle and rejects stale prices", async function () {
+    // ✅ Fixture mocks a Pyth price of $1.50 published in Nov 2023 (see Anchor.toml)
+    const oracle = new PublicKey("8WpZ3WYqXThoYLuczhW24tby1ELDRfoYqcgYpXzxxGMF");
+    if (!(await provider.connection.getAccountInfo(oracle))) {
+      this.skip();
+    }
+    const usdValue = async (name: string, maxAge: number) => {
+      const pdas = await initEscrow(name, { priceOracle: oracle, oracleMaxAgeSecs: maxAge });
+      await mintTo(provider.connection, (owner as any).payer, mint, pdas.vault, owner.publicKey, 10_000_000_000n);
+      return program.methods
+        .vaultUsdValue(name)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAuth: pdas.vaultAuth,
+          vaultAta: pdas.vault,
+          mint,
+          priceOracle: oracle,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .view();
+    };
+
+    // ✅ 10 tokens at $1.50 is $15, in micro-dollars
+    const value = await usdValue("escrow-oracle", 1_000_000_000);
+    assert.equal(value.toNumber(), 15_000_000);
+
+    try {
+      await usdValue("escrow-oracle-stale", 60);
+    } catch (err) {
+      const logs: string[] = err.simulationResponse?.logs ?? err.logs ?? [];
+      assert.isTrue(logs.some((l) => l.includes("StaleOracle")));
+      return;
+    }
+    assert.fail("Expected StaleOracle error");
+  });
+
+  it("🔴 Batched Distribution: Batches must match the plan committed by begin_distribution", async () => {
+    const name = "escrow-batched";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+    const excluded = contributors[0].publicKey;
+
+    await program.methods
+      .beginDistribution(name, 1, excluded, 0, new anchor.BN(0))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const batch = (target: PublicKey, count: number, caller: Keypair) =>
+      program.methods
+        .distributeBatch(name, 1, target, 0, new anchor.BN(0), count)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          caller: caller.publicKey,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .signers([caller])
+        .rpc();
+
+    await expectError(batch(excluded, 0, contributors[1]), "EmptyBatch");
+    const before = await balances();
+    await batch(excluded, 2, contributors[1]);
+
+    // ✅ Switching the excluded contributor midway no longer matches the commitment
+    await expectError(batch(contributors[4].publicKey, 2, contributors[1]), "PlanMismatch");
+    await expectError(distributeEscrow(name, 15, excluded), "DistributionInProgress");
+
+    // ✅ Anyone can finish the committed plan
+    await batch(excluded, 5, contributors[2]);
+    const after = await balances();
+    contributors.forEach((_, i) =>
+      assert.equal(after[i] - before[i], i === 0 ? 0n : 6n, `Contributor ${i}`)
+    );
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.distributed);
+    assert.isNull(state.pendingPlan);
+  });
+
+  it("🟢 Close Escrow: An escrow no one deposited into returns its rent to the owner", async () => {
+    const name = "escrow-close-empty";
+    const pdas = await initEscrow(name);
+    const closeEscrow = (escrowName: string, p: typeof pdas) =>
+      program.methods
+        .closeEscrow(escrowName)
+        .accounts({
+          escrow: p.escrow,
+          owner: owner.publicKey,
+          vaultAta: p.vault,
+          vaultAuth: p.vaultAuth,
+          ownerRegistry: null,
+          tombstone: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    // ✅ A pool with a deposit can't be closed before it's distributed
+    const started = await initEscrow("escrow-close-started");
+    await depositAll("escrow-close-started", [contributors[0]], [contributorAtas[0]]);
+    await expectError(closeEscrow("escrow-close-started", started), "PoolAlreadyStarted");
+
+    const rent =
+      (await provider.connection.getBalance(pdas.escrow)) +
+      (await provider.connection.getBalance(pdas.vault));
+    const before = await provider.connection.getBalance(owner.publicKey);
+    await closeEscrow(name, pdas);
+    const after = await provider.connection.getBalance(owner.publicKey);
+
+    assert.isNull(await provider.connection.getAccountInfo(pdas.escrow));
+    assert.isNull(await provider.connection.getAccountInfo(pdas.vault));
+    assert.isAbove(after - before, rent - 10_000, "Rent back, minus the fee");
+  });
+
+  it("🔴 Distribute Mode 14: Stray vault tokens the books don't record fail with AccountingMismatch", async () => {
+    const name = "escrow-accounting-mismatch";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    // ✅ Tokens sent straight to the vault, not through deposit or owner_topup
+    await mintTo(provider.connection, (owner as any).payer, mint, pdas.vault, owner.publicKey, 3);
+
+    await expectError(distributeEscrow(name, 14, contributors[0].publicKey), "AccountingMismatch");
+
+    // ✅ Modes that don't weight the split still pay out the whole vault
+    await distributeEscrow(name, 15, contributors[0].publicKey);
+  });
+
+  it("🟢 Token-2022: A Token-2022 mint deposits and distributes through the token interface", async () => {
+    const name = "escrow-token-2022";
+    const payer = (owner as any).payer;
+    const tokenMint = mint;
+    mint = await createMint(
+      provider.connection,
+      payer,
+      owner.publicKey,
+      null,
+      9,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    try {
+      const kps = contributors.slice(0, 2);
+      const atas: PublicKey[] = [];
+      for (const kp of kps) {
+        const ata = await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          payer,
+          mint,
+          kp.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        await mintTo(provider.connection, payer, mint, ata.address, owner.publicKey, 5, [], undefined, TOKEN_2022_PROGRAM_ID);
+        atas.push(ata.address);
+      }
+
+      const pdas = await initEscrow(name, { targetTotal: 10, tokenProgram: TOKEN_2022_PROGRAM_ID });
+      const deposit = (i: number, tokenProgram: PublicKey) =>
+        program.methods
+          .deposit(name, new anchor.BN(5), false, "")
+          .accounts({
+            escrow: pdas.escrow,
+            contributor: kps[i].publicKey,
+            contributorAta: atas[i],
+            vaultAta: pdas.vault,
+            vaultAuth: pdas.vaultAuth,
+            attestation: null,
+            gateAta: null,
+            mint,
+            tokenProgram,
+          })
+          .signers([kps[i]])
+          .rpc();
+
+      // ✅ The token program must be the one that owns the mint
+      await expectError(deposit(0, TOKEN_PROGRAM_ID), "ConstraintMintTokenProgram");
+
+      await deposit(0, TOKEN_2022_PROGRAM_ID);
+
+      // ✅ Delegated deposits derive the vault with the same token program
+      const delegate = Keypair.generate();
+      await approve(provider.connection, payer, atas[1], delegate.publicKey, kps[1], 5n, [], undefined, TOKEN_2022_PROGRAM_ID);
+      await program.methods
+        .depositDelegated(name, new anchor.BN(5))
+        .accounts({
+          escrow: pdas.escrow,
+          delegate: delegate.publicKey,
+          contributorAta: atas[1],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([delegate])
+        .rpc();
+
+      await program.methods
+        .distribute(name, 15, PublicKey.default, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(atas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false })))
+        .rpc();
+
+      for (const ata of atas) {
+        const account = await getAccount(provider.connection, ata, undefined, TOKEN_2022_PROGRAM_ID);
+        assert.equal(account.amount, 5n, "Each contributor gets their share back");
+      }
+    } finally {
+      mint = tokenMint;
+    }
+  });
+
+  it("🟢 Tags: Set at init, replaced by the owner and capped at 8", async () => {
+    const name = "escrow-tags";
+    const pdas = await initEscrow(name, { tags: [1, 42] });
+    assert.deepEqual((await program.account.escrowState.fetch(pdas.escrow)).tags, [1, 42]);
+
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+    await program.methods.setTags(name, [7]).accounts(ownerUpdate).rpc();
+    const config = await program.methods.getConfig(name).accounts({ escrow: pdas.escrow }).view();
+    assert.deepEqual(config.tags, [7]);
+
+    await expectError(
+      program.methods.setTags(name, [1, 2, 3, 4, 5, 6, 7, 8, 9]).accounts(ownerUpdate).rpc(),
+      "TooManyTags"
+    );
+    await expectError(
+      program.methods.setTags(name, []).accounts({ escrow: pdas.escrow, owner: contributors[0].publicKey }).signers([contributors[0]]).rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("🟢 Distribute Mode 17: The last depositor gets a bonus on top of an equal share", async () => {
+    const name = "escrow-last-depositor-bonus";
+    await initEscrow(name);
+    // ✅ contributors[4] fills the pool
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(
+      distributeEscrow(name, 17, PublicKey.default, { winnerBps: 10_001 }),
+      "InvalidBps"
+    );
+
+    const before = await balances();
+    await distributeEscrow(name, 17, PublicKey.default, { winnerBps: 2_000 });
+    const after = await balances();
+
+    // ✅ 25 in the vault: a 5 bonus, then 20 split 4 each
+    contributors.forEach((_, i) =>
+      assert.equal(after[i] - before[i], i === 4 ? 9n : 4n, `Contributor ${i} payout`)
+    );
+  });
+
+  it("🔴 Refund: A short vault fails with VaultShortfall unless the refund takes a pro-rata haircut", async () => {
+    const name = "escrow-vault-shortfall";
+    const payer = (owner as any).payer;
+    const tokenMint = mint;
+
+    // ✅ A 50% transfer fee leaves 2 of each 5-token deposit in the vault
+    const feeMint = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: feeMint.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          feeMint.publicKey,
+          owner.publicKey,
+          owner.publicKey,
+          5_000,
+          5n,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(feeMint.publicKey, 9, owner.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [payer, feeMint]
+    );
+    mint = feeMint.publicKey;
+    try {
+      const kps = contributors.slice(0, 2);
+      const atas: PublicKey[] = [];
+      for (const kp of kps) {
+        const ata = await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          payer,
+          mint,
+          kp.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        await mintTo(provider.connection, payer, mint, ata.address, owner.publicKey, 5, [], undefined, TOKEN_2022_PROGRAM_ID);
+        atas.push(ata.address);
+      }
+
+      const pdas = await initEscrow(name, { tokenProgram: TOKEN_2022_PROGRAM_ID });
+      for (let i = 0; i < kps.length; i++) {
+        await program.methods
+          .deposit(name, new anchor.BN(5), false, "")
+          .accounts({
+            escrow: pdas.escrow,
+            contributor: kps[i].publicKey,
+            contributorAta: atas[i],
+            vaultAta: pdas.vault,
+            vaultAuth: pdas.vaultAuth,
+            attestation: null,
+            gateAta: null,
+            mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([kps[i]])
+          .rpc();
+      }
+
+      const refund = (i: number, acceptHaircut: boolean) =>
+        program.methods
+          .refund(name, acceptHaircut)
+          .accounts({
+            escrow: pdas.escrow,
+            contributor: kps[i].publicKey,
+            contributorAta: atas[i],
+            refundDestination: null,
+            vaultAta: pdas.vault,
+            vaultAuth: pdas.vaultAuth,
+            mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([kps[i]])
+          .rpc();
+      const vaultAmount = async () =>
+        (await getAccount(provider.connection, pdas.vault, undefined, TOKEN_2022_PROGRAM_ID)).amount;
+
+      await expectError(refund(0, false), "VaultShortfall");
+
+      // ✅ 4 of 10 recorded tokens in the vault: each refund of 5 pays 2
+      await refund(0, true);
+      assert.equal(await vaultAmount(), 2n);
+      await refund(1, true);
+      assert.equal(await vaultAmount(), 0n, "The last one out takes what is left");
+    } finally {
+      mint = tokenMint;
+    }
+  });
+
+  it("🟢 Merkle Claims: Partial claims add up to the allotment and no further", async () => {
+    const name = "escrow-partial-claims";
+    const root = Buffer.from(
+      keccak_256(
+        Buffer.concat([contributors[0].publicKey.toBuffer(), new anchor.BN(10).toArrayLike(Buffer, "le", 8)])
+      )
+    );
+    const pdas = await initEscrow(name, { merkleRoot: Array.from(root) });
+    await depositAll(name, contributors, contributorAtas);
+    await topUp(name, 10);
+
+    const [claimRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from("claim"), pdas.escrow.toBuffer(), contributors[0].publicKey.toBuffer()],
+      program.programId
+    );
+    const claim = (amount: number) =>
+      program.methods
+        .claimWithProof(name, new anchor.BN(10), new anchor.BN(amount), [])
+        .accounts({
+          escrow: pdas.escrow,
+          claimant: contributors[0].publicKey,
+          claimRecord,
+          claimantAta: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([contributors[0]])
+        .rpc();
+
+    const before = await balances();
+    await claim(4);
+    await expectError(claim(7), "NothingToClaim");
+    await claim(6);
+    const after = await balances();
+    assert.equal(after[0] - before[0], 10n, "Two partial claims add up to the allotment");
+
+    const record = await program.account.claimRecord.fetch(claimRecord);
+    assert.equal(record.claimedAmount.toNumber(), 10);
+    await expectError(claim(1), "AlreadyClaimed");
+  });
+
+  it("🔴 Account Age: Wallets the attester first saw too recently fail with AccountTooNew", async () => {
+    const name = "escrow-account-age";
+    const attester = Keypair.generate();
+    await airdrop(attester.publicKey);
+
+    await expectError(initEscrow("escrow-age-no-attester", { minAccountAgeSlots: 100 }), "AgeCheckNeedsAttester");
+    const pdas = await initEscrow(name, { attester: attester.publicKey, minAccountAgeSlots: 100 });
+
+    const attestationFor = (subject: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("attestation"), attester.publicKey.toBuffer(), subject.toBuffer()],
+        program.programId
+      )[0];
+    const slot = await provider.connection.getSlot();
+    const now = Math.floor(Date.now() / 1000);
+    const attest = (i: number, firstSeenSlot: number) =>
+      program.methods
+        .attest(contributors[i].publicKey, new anchor.BN(now + 3600), new anchor.BN(firstSeenSlot))
+        .accounts({
+          attestation: attestationFor(contributors[i].publicKey),
+          attester: attester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([attester])
+        .rpc();
+    const depositFrom = (i: number) =>
+      program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: attestationFor(contributors[i].publicKey),
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    // ✅ Seen just now, and not vouched for at all
+    await attest(0, slot);
+    await attest(1, 0);
+    await expectError(depositFrom(0), "AccountTooNew");
+    await expectError(depositFrom(1), "AccountTooNew");
+
+    // ✅ Re-attested as seen well before the minimum age
+    await attest(0, Math.max(1, slot - 1_000));
+    await depositFrom(0);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+  });
+
+  it("🟢 Progress: Reports fill progress in basis points at 0%, 50% and 100%", async () => {
+    const name = "escrow-progress";
+    const pdas = await initEscrow(name, { targetTotal: 10 });
+    const progress = async (escrowName: string, escrow: PublicKey) =>
+      program.methods.progressBps(escrowName).accounts({ escrow }).view();
+
+    assert.equal(await progress(name, pdas.escrow), 0);
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+    assert.equal(await progress(name, pdas.escrow), 5_000);
+    await depositAll(name, [contributors[1]], [contributorAtas[1]]);
+    assert.equal(await progress(name, pdas.escrow), 10_000);
+
+    // ✅ Count-based pools measure funded slots out of 5
+    const counted = await initEscrow("escrow-progress-count");
+    await depositAll("escrow-progress-count", contributors.slice(0, 2), contributorAtas.slice(0, 2));
+    assert.equal(await progress("escrow-progress-count", counted.escrow), 4_000);
+  });
+
+  it("🟢 Accepted Mints: Slots fill in either accepted mint and are tracked per mint", async () => {
+    const name = "escrow-accepted-mints";
+    const payer = (owner as any).payer;
+    const altMint = await createMint(provider.connection, payer, owner.publicKey, null, 6);
+    const strayMint = await createMint(provider.connection, payer, owner.publicKey, null, 6);
+    await expectError(initEscrow("escrow-accepted-self", { acceptedMints: [mint] }), "InvalidAcceptedMints");
+    const pdas = await initEscrow(name, { acceptedMints: [altMint] });
+
+    // ✅ The alt-mint vault is the vault authority's ATA, created by whoever needs it first
+    const altVault = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, altMint, pdas.vaultAuth, true)
+    ).address;
+    const altAta = await createAccount(provider.connection, payer, altMint, contributors[1].publicKey);
+    await mintTo(provider.connection, payer, altMint, altAta, owner.publicKey, 1_000n);
+    const strayAta = await createAccount(provider.connection, payer, strayMint, contributors[2].publicKey);
+    await mintTo(provider.connection, payer, strayMint, strayAta, owner.publicKey, 1_000n);
+
+    const deposit = (i: number, depositMint: PublicKey, source: PublicKey, vault: PublicKey) =>
+      program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: source,
+          vaultAta: vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint: depositMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    await deposit(0, mint, contributorAtas[0], pdas.vault);
+    await expectError(deposit(1, altMint, altAta, pdas.vault), "InvalidVault");
+    await deposit(1, altMint, altAta, altVault);
+    await expectError(deposit(2, strayMint, strayAta, altVault), "UnknownMint");
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.deepEqual(
+      state.depositMints.map((m: PublicKey) => m.toBase58()),
+      [mint.toBase58(), altMint.toBase58()]
+    );
+    const depositedIn = (m: PublicKey) =>
+      program.methods.depositedInMint(name, m).accounts({ escrow: pdas.escrow }).view();
+    assert.equal((await depositedIn(mint)).toNumber(), 5);
+    assert.equal((await depositedIn(altMint)).toNumber(), 5);
+    assert.equal((await getAccount(provider.connection, altVault)).amount, 5n);
+
+    // ✅ A refund pays the slot back in the mint it was funded with
+    await program.methods
+      .refund(name, false)
+      .accounts({
+        escrow: pdas.escrow,
+        contributor: contributors[1].publicKey,
+        contributorAta: altAta,
+        refundDestination: null,
+        vaultAta: altVault,
+        vaultAuth: pdas.vaultAuth,
+        mint: altMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([contributors[1]])
+      .rpc();
+    assert.equal((await getAccount(provider.connection, altAta)).amount, 1_000n);
+  });
+
+  it("🟢 Opt-In: Only opted-in recipients are paid, held shares follow once the rest opt in", async () => {
+    const name = "escrow-opt-in";
+    const pdas = await initEscrow(name, { optInRequired: true });
+    await depositAll(name, contributors, contributorAtas);
+    const optIn = (kp: Keypair) =>
+      program.methods
+        .optIn(name)
+        .accounts({ escrow: pdas.escrow, contributor: kp.publicKey })
+        .signers([kp])
+        .rpc();
+
+    await expectError(distributeEscrow(name, 15, PublicKey.default), "NoOptedInRecipients");
+    await optIn(contributors[0]);
+    await optIn(contributors[1]);
+    await expectError(optIn(contributors[1]), "AlreadyOptedIn");
+
+    const before = await balances();
+    await distributeEscrow(name, 15, PublicKey.default);
+    const mid = await balances();
+    assert.deepEqual(
+      mid.map((b, i) => b - before[i]),
+      [5n, 5n, 0n, 0n, 0n]
+    );
+    let state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.heldPayouts.length, 3);
+    assert.equal((await getAccount(provider.connection, pdas.vault)).amount, 15n);
+
+    // ✅ A later pass pays whoever opted in since and keeps holding the rest
+    await optIn(contributors[2]);
+    await program.methods
+      .distributeHeld(name)
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(contributorAtas.map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false })))
+      .rpc();
+    const after = await balances();
+    assert.equal(after[2] - mid[2], 5n);
+    assert.equal(after[3], mid[3]);
+    state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.heldPayouts.length, 2);
+  });
+
+  it("🔴 Committed Plan: Distribution must match the committed payouts", async () => {
+    const name = "escrow-commit-plan";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+    const commit = (mode: number, target: PublicKey) =>
+      program.methods
+        .commitPlan(name, mode, target, 0, new anchor.BN(0))
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          vaultAuth: pdas.vaultAuth,
+          vaultAta: pdas.vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    await commit(15, PublicKey.default);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isNotNull(state.committedPlan);
+    assert.isAbove(state.planCommittedAt.toNumber(), 0);
+    await expectError(commit(1, contributors[0].publicKey), "PlanAlreadyCommitted");
+
+    // ✅ Any other mode or target changes the payouts and is refused
+    await expectError(distributeEscrow(name, 1, contributors[0].publicKey), "PlanHashMismatch");
+    await expectError(distributeEscrow(name, 0, contributors[0].publicKey), "PlanHashMismatch");
+
+    const before = await balances();
+    await distributeEscrow(name, 15, PublicKey.default);
+    const after = await balances();
+    assert.deepEqual(
+      after.map((b, i) => b - before[i]),
+      [5n, 5n, 5n, 5n, 5n]
+    );
+  });
+
+  it("🟢 Accepted Mints: Each contributor is refunded in the mint they deposited", async () => {
+    const name = "escrow-mixed-refunds";
+    const payer = (owner as any).payer;
+    const altMint = await createMint(provider.connection, payer, owner.publicKey, null, 6);
+    const pdas = await initEscrow(name, { acceptedMints: [altMint] });
+    const altVault = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, altMint, pdas.vaultAuth, true)
+    ).address;
+    const altAtas: PublicKey[] = [];
+    for (const kp of contributors.slice(0, 2)) {
+      const ata = await createAccount(provider.connection, payer, altMint, kp.publicKey);
+      await mintTo(provider.connection, payer, altMint, ata, owner.publicKey, 1_000n);
+      altAtas.push(ata);
+    }
+
+    // ✅ Contributor 0 pays in the escrow mint, contributor 1 in the alternative
+    const slots = [
+      { mint, source: contributorAtas[0], vault: pdas.vault },
+      { mint: altMint, source: altAtas[1], vault: altVault },
+    ];
+    for (const [i, slot] of slots.entries()) {
+      await program.methods
+        .deposit(name, new anchor.BN(5), false, "")
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: slot.source,
+          vaultAta: slot.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint: slot.mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+    }
+
+    const refund = (i: number, refundMint: PublicKey, destination: PublicKey, vault: PublicKey) =>
+      program.methods
+        .refund(name, false)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: destination,
+          refundDestination: null,
+          vaultAta: vault,
+          vaultAuth: pdas.vaultAuth,
+          mint: refundMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    // ✅ Asking for the other mint is refused for either slot
+    await expectError(refund(0, altMint, altAtas[0], altVault), "UnknownMint");
+    await expectError(refund(1, mint, contributorAtas[1], pdas.vault), "UnknownMint");
+
+    const before = await balances();
+    await refund(0, mint, contributorAtas[0], pdas.vault);
+    await refund(1, altMint, altAtas[1], altVault);
+    const after = await balances();
+    assert.equal(after[0] - before[0], 5n);
+    assert.equal(after[1], before[1], "Nothing comes back in the escrow mint");
+    assert.equal((await getAccount(provider.connection, altAtas[1])).amount, 1_000n);
+    assert.equal((await getAccount(provider.connection, pdas.vault)).amount, 0n);
+    assert.equal((await getAccount(provider.connection, altVault)).amount, 0n);
+  });
+
+  it("🔴 Owner Registry: Registered owners can't hold more than the maximum of open escrows", async () => {
+    const [ownerRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("owner-reg"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initOwnerRegistry()
+      .accounts({ ownerRegistry, owner: owner.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    const limit = 16;
+    const names = Array.from({ length: limit }, (_, i) => `escrow-registry-${i}`);
+    for (const name of names) {
+      await initEscrow(name, { ownerRegistry });
+    }
+    assert.equal((await program.account.ownerRegistry.fetch(ownerRegistry)).escrowCount, limit);
+    await expectError(initEscrow("escrow-registry-over", { ownerRegistry }), "OwnerEscrowLimit");
+
+    const closeEscrow = (name: string, registry: PublicKey | null) =>
+      deriveEscrow(name).then((p) =>
+        program.methods
+          .closeEscrow(name)
+          .accounts({
+            escrow: p.escrow,
+            owner: owner.publicKey,
+            vaultAta: p.vault,
+            vaultAuth: p.vaultAuth,
+            ownerRegistry: registry,
+            tombstone: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc()
+      );
+
+    // ✅ Closing a counted escrow frees its place, but only through the registry
+    await expectError(closeEscrow(names[0], null), "OwnerRegistryMissing");
+    await closeEscrow(names[0], ownerRegistry);
+    assert.equal((await program.account.ownerRegistry.fetch(ownerRegistry)).escrowCount, limit - 1);
+    await initEscrow("escrow-registry-over", { ownerRegistry });
+  });
+
+  it("🟢 Distribute Mode 14: A late depositor's tiny share is raised to the minimum payout", async () => {
+    const name = "escrow-payout-floor";
+    await initEscrow(name, { minPayout: 2 });
+    await depositAll(name, contributors.slice(0, 4), contributorAtas.slice(0, 4));
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await depositAll(name, [contributors[4]], [contributorAtas[4]]);
+
+    // ✅ At most a second in the pool against 3+ for the others: 1 token or less unfloored
+    const before = await balances();
+    await distributeEscrow(name, 14, contributors[0].publicKey);
+    const after = await balances();
+    const paid = contributors.map((_, i) => after[i] - before[i]);
+    assert.equal(paid[4], 2n);
+    assert.equal(paid.reduce((a, b) => a + b, 0n), 25n, "Whole vault is paid out");
+
+    // ✅ 5 recipients at a floor of 6 need 30 of the 25 tokens
+    const unaffordable = "escrow-payout-floor-high";
+    await initEscrow(unaffordable, { minPayout: 6 });
+    await depositAll(unaffordable, contributors, contributorAtas);
+    await expectError(distributeEscrow(unaffordable, 14, contributors[0].publicKey), "FloorUnaffordable");
+  });
+
+  it("🟢 Transfer Slot: A contributor hands their funded slot to another wallet", async () => {
+    const name = "escrow-transfer-slot";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors.slice(0, 2), contributorAtas.slice(0, 2));
+    const buyer = Keypair.generate();
+    await airdrop(buyer.publicKey);
+    const buyerAta = await createAccount(provider.connection, (owner as any).payer, mint, buyer.publicKey);
+
+    const transfer = (from: Keypair, to: PublicKey) =>
+      program.methods
+        .transferSlot(name, to)
+        .accounts({ escrow: pdas.escrow, contributor: from.publicKey, attestation: null, gateAta: null })
+        .signers([from])
+        .rpc();
+
+    await expectError(transfer(contributors[0], contributors[1].publicKey), "SlotTransferConflict");
+    await transfer(contributors[0], buyer.publicKey);
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.contributors[0].equals(buyer.publicKey));
+    assert.equal(state.amounts[0].toNumber(), 5, "The deposit stays with the slot");
+    assert.equal((await getAccount(provider.connection, pdas.vault)).amount, 10n, "No tokens moved");
+
+    // ✅ The slot, and its refund, now belong to the buyer
+    const refund = (kp: Keypair, ata: PublicKey) =>
+      program.methods
+        .refund(name, false)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: kp.publicKey,
+          contributorAta: ata,
+          refundDestination: null,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([kp])
+        .rpc();
+    await expectError(refund(contributors[0], contributorAtas[0]), "NotAContributor");
+    await refund(buyer, buyerAta);
+    assert.equal((await getAccount(provider.connection, buyerAta)).amount, 5n);
+  });
+
+  it("🟢 Accessors: get_owner and get_mint return the escrow's owner and mint", async () => {
+    const name = "escrow-accessors";
+    const pdas = await initEscrow(name);
+
+    const escrowOwner: PublicKey = await program.methods.getOwner(name).accounts({ escrow: pdas.escrow }).view();
+    const escrowMint: PublicKey = await program.methods.getMint(name).accounts({ escrow: pdas.escrow }).view();
+    assert.isTrue(escrowOwner.equals(owner.publicKey));
+    assert.isTrue(escrowMint.equals(mint));
+  });
+
+  it("🟢 Dust To Owner: The owner receives what an uneven split leaves and the vault ends empty", async () => {
+    const name = "escrow-dust-to-owner";
+    const pdas = await initEscrow(name, { dustToOwner: true });
+    await depositAll(name, contributors, contributorAtas);
+    const ownerAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, (owner as any).payer, mint, owner.publicKey)
+    ).address;
+
+    // ✅ Mode 1 splits 25 among the 4 others: 6 each, 1 left over
+    await expectError(distributeEscrow(name, 1, contributors[0].publicKey), "MissingOwnerAta");
+    const ownerBefore = (await getAccount(provider.connection, ownerAta)).amount;
+    const before = await balances();
+    await distributeEscrow(name, 1, contributors[0].publicKey, { ownerAta });
+    const after = await balances();
+
+    assert.deepEqual(
+      after.map((b, i) => b - before[i]),
+      [0n, 6n, 6n, 6n, 6n]
+    );
+    assert.equal((await getAccount(provider.connection, ownerAta)).amount - ownerBefore, 1n);
+    assert.equal((await getAccount(provider.connection, pdas.vault)).amount, 0n);
+  });
+
+  it("🔴 Distribute: A mode past the highest known one fails with UnknownMode", async () => {
+    const name = "escrow-unknown-mode";
+    await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(distributeEscrow(name, 200, contributors[0].publicKey), "UnknownMode");
+    await expectError(initEscrow("escrow-unknown-auto-mode", { autoMode: 200 }), "UnknownMode");
+    // ✅ Gaps below the highest known mode are still plain InvalidMode
+    await expectError(distributeEscrow(name, 5, contributors[0].publicKey), "InvalidMode");
+  });
+
+  it("🟢 Referral Codes: A deposit made with a code records the referrer it resolves to", async () => {
+    const name = "escrow-referral-code";
+    const pdas = await initEscrow(name);
+    const referrer = contributors[4];
+    const codePda = (code: string) =>
+      PublicKey.findProgramAddressSync([Buffer.from("ref"), Buffer.from(code)], program.programId)[0];
+
+    await program.methods
+      .registerReferralCode("BONK42")
+      .accounts({
+        referralCode: codePda("BONK42"),
+        referrer: referrer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([referrer])
+      .rpc();
+
+    const depositWithCode = (i: number, code: string) =>
+      program.methods
+        .depositWithCode(name, new anchor.BN(5), code)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[i].publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          referralCode: codePda(code),
+          attestation: null,
+          gateAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[i]])
+        .rpc();
+
+    await expectError(depositWithCode(0, "NOSUCHCODE"), "UnknownReferralCode");
+    await depositWithCode(0, "BONK42");
+    await depositAll(name, [contributors[1]], [contributorAtas[1]]);
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.referrers[0].equals(referrer.publicKey));
+    assert.isTrue(state.referrers[1].equals(PublicKey.default), "A plain deposit names no referrer");
+    assert.equal(state.amounts[0].toNumber(), 5);
+  });
+
+  it("🔴 Initialize: Rejects a program id or a nonsensical mint as the escrow mint", async () => {
+    const tokenMint = mint;
+    try {
+      // ✅ A program account never deserializes as a mint
+      mint = TOKEN_PROGRAM_ID;
+      await expectError(initEscrow("escrow-mint-program"), "AccountOwnedByWrongProgram");
+
+      // ✅ A real mint, but with more decimals than any sane token
+      mint = await createMint(provider.connection, (owner as any).payer, owner.publicKey, null, 30);
+      await expectError(initEscrow("escrow-mint-decimals"), "InvalidMint");
+    } finally {
+      mint = tokenMint;
+    }
+  });
+
+  it("🟢 Distribute Ranked: A 3-way ranking pays each place its share, dust to first", async () => {
+    const name = "escrow-ranked";
+    const pdas = await initEscrow(name, { targetTotal: 15 });
+    await depositAll(name, contributors.slice(0, 3), contributorAtas.slice(0, 3));
+
+    const distributeRanked = (ranking: PublicKey[], rankBps: number[]) =>
+      program.methods
+        .distributeRanked(name, ranking, rankBps)
+        .accounts({
+          escrow: pdas.escrow,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          owner: owner.publicKey,
+          auditLog: pdas.audit,
+          mint,
+          targetEscrow: null,
+          targetVaultAta: null,
+          ownerAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          contributorAtas.slice(0, 3).map((ata) => ({ pubkey: ata, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+
+    const [first, second, third] = [contributors[2], contributors[0], contributors[1]].map((c) => c.publicKey);
+    await expectError(distributeRanked([first, second, second], [5_000, 3_000, 2_000]), "InvalidRanking");
+    await expectError(distributeRanked([first, second], [5_000, 5_000]), "InvalidRanking");
+    await expectError(distributeRanked([first, second, third], [5_000, 3_000, 1_000]), "InvalidBps");
+
+    const before = await balances();
+    await distributeRanked([first, second, third], [5_000, 3_000, 2_000]);
+    const after = await balances();
+
+    // ✅ 15 in the vault: 7 / 4 / 3 by bps, the leftover 1 to first place
+    assert.equal(after[2] - before[2], 8n);
+    assert.equal(after[0] - before[0], 4n);
+    assert.equal(after[1] - before[1], 3n);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.distributed);
+  });
+
+  it("🔴 Close: A closed escrow's address can't be re-initialized during its cooldown", async () => {
+    const name = "escrow-reinit-cooldown";
+    const pdas = await initEscrow(name, { reinitCooldownSecs: 3_600 });
+    const closeEscrow = (tombstone: PublicKey | null) =>
+      program.methods
+        .closeEscrow(name)
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          ownerRegistry: null,
+          tombstone,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    await expectError(closeEscrow(null), "MissingTombstone");
+    await closeEscrow(pdas.tombstone);
+
+    const tombstone = await program.account.escrowTombstone.fetch(pdas.tombstone);
+    assert.isTrue(tombstone.escrow.equals(pdas.escrow));
+    assert.equal(tombstone.reopensAt.toNumber() - tombstone.closedAt.toNumber(), 3_600);
+    await expectError(initEscrow(name), "RecentlyClosed");
+  });
+
+  it("🟢 Batch Vault Info: Reports several escrows' vault balances in one call", async () => {
+    const names = ["escrow-batch-a", "escrow-batch-b", "escrow-batch-c"];
+    const all = [];
+    for (const [i, name] of names.entries()) {
+      all.push(await initEscrow(name));
+      // ✅ A different fill level per pool: 0, 5 and 10 tokens
+      await depositAll(name, contributors.slice(0, i), contributorAtas.slice(0, i));
+    }
+
+    const batchVaultInfo = (accounts: PublicKey[]) =>
+      program.methods
+        .batchVaultInfo()
+        .accounts({})
+        .remainingAccounts(accounts.map((pubkey) => ({ pubkey, isWritable: false, isSigner: false })))
+        .view();
+
+    const infos = await batchVaultInfo(all.flatMap((p) => [p.escrow, p.vault]));
+    assert.equal(infos.length, 3);
+    infos.forEach((info, i) => {
+      assert.isTrue(info.escrow.equals(all[i].escrow));
+      assert.equal(info.balance.toNumber(), 5 * i);
+    });
+
+    await expectError(batchVaultInfo([all[0].escrow, all[1].vault]), "InvalidVault");
+    await expectError(batchVaultInfo([all[0].escrow]), "InvalidBatchAccounts");
+  });
+
+  it("🟢 Distribute Vesting: Recipients claim their share as it vests", async () => {
+    const name = "escrow-vesting";
+    const pdas = await initEscrow(name);
+    await depositAll(name, contributors, contributorAtas);
+
+    // ✅ 5 each, nothing before a 3s cliff, everything after 6s
+    const start = Math.floor(Date.now() / 1000);
+    await program.methods
+      .distributeVesting(name, new anchor.BN(start), new anchor.BN(3), new anchor.BN(6))
+      .accounts({
+        escrow: pdas.escrow,
+        vaultAta: pdas.vault,
+        vaultAuth: pdas.vaultAuth,
+        owner: owner.publicKey,
+        auditLog: pdas.audit,
+        mint,
+        targetEscrow: null,
+        targetVaultAta: null,
+        ownerAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const claimVested = () =>
+      program.methods
+        .claimVested(name)
+        .accounts({
+          escrow: pdas.escrow,
+          recipient: contributors[0].publicKey,
+          recipientAta: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[0]])
+        .rpc();
+
+    const before = (await balances())[0];
+    await expectError(claimVested(), "NothingVested");
+
+    // ✅ Past the cliff: part of the allocation
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await claimVested();
+    const partial = (await balances())[0] - before;
+    assert.isTrue(partial > 0n && partial < 5n, `Partially vested claim was ${partial}`);
+
+    // ✅ Past the end: the rest, and nothing after that
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await claimVested();
+    assert.equal((await balances())[0] - before, 5n);
+    await expectError(claimVested(), "NothingVested");
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.distributed);
+    assert.equal(state.vestingGrants[0].claimed.toNumber(), 5);
+    assert.equal(state.vestingGrants[1].claimed.toNumber(), 0);
+
+    // ✅ Closing now would strand the other recipients' grants
+    await expectError(
+      program.methods
+        .closeEscrow(name)
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          ownerRegistry: null,
+          tombstone: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc(),
+      "UnclaimedPayouts"
+    );
+  });
+
+  it("🟢 PDA Owner: A multisig vault PDA owns an escrow and acts on it through CPI", async () => {
+    const name = "escrow-pda-owner";
+    await expectError(initEscrow(name, { ownerIsPda: true }), "OwnerNotPda");
+
+    // ✅ The vault pays the escrow's rent, so it needs lamports
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({ fromPubkey: owner.publicKey, toPubkey: multisigVault, lamports: 1e9 })
+      )
+    );
+    const pdas = await initEscrow(name, { ownerIsPda: true, multisigOwner: true });
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.owner.equals(multisigVault));
+    assert.isTrue(state.ownerIsPda);
+
+    const setTags = (signer: PublicKey) =>
+      program.methods.setTags(name, [7]).accounts({ escrow: pdas.escrow, owner: signer });
+    await expectError(setTags(owner.publicKey).rpc(), "Unauthorized");
+    await multisigExecute(await setTags(multisigVault).instruction());
+    assert.deepEqual((await program.account.escrowState.fetch(pdas.escrow)).tags, [7]);
+  });
+
+  it("🟢 Repair Vectors: The owner pads per-slot vectors that fell behind contributors", async function () {
+    // ✅ Fixture mocks a version 1, 2-slot escrow missing amounts, timestamps, mints and referrers
+    // (see Anchor.toml); its owner is derived from a fixed seed so the test can sign
+    const fixture = new PublicKey("7JBHnXEENqnFZ56Dy7HXYmLEBzn8XrmFrtXwxacmKBpN");
+    if (!(await provider.connection.getAccountInfo(fixture))) {
+      this.skip();
+    }
+    const fixtureOwner = Keypair.fromSeed(new Uint8Array(32).fill(9));
+    const repair = (signer: Keypair) =>
+      program.methods
+        .repairVectors("escrow-truncated")
+        .accounts({ escrow: fixture, owner: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    await expectError(repair(contributors[0]), "Unauthorized");
+    await repair(fixtureOwner);
+
+    const state = await program.account.escrowState.fetch(fixture);
+    assert.equal(state.contributors.length, 2);
+    // ✅ The slot whose amount was lost is recorded as unfunded rather than as a deposit
+    assert.deepEqual(state.amounts.map((a) => a.toNumber()), [5, 0]);
+    assert.deepEqual(state.refunded, [false, true]);
+    assert.deepEqual(state.depositedAt.map((t) => t.toNumber()), [0, 0]);
+    assert.equal(state.depositMints.length, 2);
+    assert.isTrue(state.depositMints.every((m) => m.equals(state.tokenMint)));
+    assert.equal(state.referrers.length, 2);
+    assert.isTrue(state.payers.every((p, i) => p.equals(state.contributors[i])));
+
+    // ✅ Repair upgrades the escrow past the layout version that could fall behind
+    assert.equal(state.version, 2);
+    await expectError(repair(fixtureOwner), "UnsupportedVersion");
+  });
+
+  it("🔴 Target Whitelist: Mode 0 only pays a whitelisted contributor", async () => {
+    const name = "escrow-target-whitelist";
+    const outsider = Keypair.generate().publicKey;
+    await initEscrow(name, { targetWhitelist: [contributors[1].publicKey, outsider] });
+    await depositAll(name, contributors, contributorAtas);
+
+    await expectError(distributeEscrow(name, 0, contributors[0].publicKey), "TargetNotWhitelisted");
+    // ✅ Whitelisted, but still has to be a contributor
+    await expectError(distributeEscrow(name, 0, outsider), "InvalidTarget");
+
+    const before = await balances();
+    await distributeEscrow(name, 0, contributors[1].publicKey);
+    const after = await balances();
+    assert.equal(after[1] - before[1], 25n);
+  });
+
+  it("🔴 Unique Payers: A pool one wallet funded for everyone can't be distributed", async () => {
+    const name = "escrow-unique-payers";
+    const pdas = await initEscrow(name, { minUniquePayers: 2 });
+    const delegate = Keypair.generate();
+    await airdrop(delegate.publicKey);
+
+    // ✅ Every contributor approves the same delegate, which then funds every slot
+    for (const [i, kp] of contributors.entries()) {
+      await approve(provider.connection, (owner as any).payer, contributorAtas[i], delegate.publicKey, kp, 5n);
+      await program.methods
+        .depositDelegated(name, new anchor.BN(5))
+        .accounts({
+          escrow: pdas.escrow,
+          delegate: delegate.publicKey,
+          contributorAta: contributorAtas[i],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          attestation: null,
+          gateAta: null,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([delegate])
+        .rpc();
+    }
+
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isTrue(state.payers.every((p) => p.equals(delegate.publicKey)));
+    await expectError(distributeEscrow(name, 15, PublicKey.default), "InsufficientUniquePayers");
+
+    // ✅ Feeding the pool into another escrow is a distribution too
+    const outer = await initEscrow("escrow-unique-payers-outer");
+    await expectError(
+      distributeEscrow(name, 0, outer.escrow, {
+        targetEscrow: outer.escrow,
+        targetVaultAta: outer.vault,
+        remaining: [],
+      }),
+      "InsufficientUniquePayers"
+    );
+  });
+
+  it("🔴 Freeze All: A frozen escrow refuses deposits, refunds, distribution and owner updates", async () => {
+    const name = "escrow-freeze-all";
+    const pdas = await initEscrow(name);
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+
+    await expectError(
+      program.methods.freezeAll(name).accounts({ escrow: pdas.escrow, owner: contributors[0].publicKey }).signers([contributors[0]]).rpc(),
+      "Unauthorized"
+    );
+    await program.methods.freezeAll(name).accounts(ownerUpdate).rpc();
+
+    await expectError(depositAll(name, [contributors[1]], [contributorAtas[1]]), "EscrowFrozen");
+    await expectError(
+      program.methods
+        .refund(name, false)
+        .accounts({
+          escrow: pdas.escrow,
+          contributor: contributors[0].publicKey,
+          contributorAta: contributorAtas[0],
+          refundDestination: contributorAtas[0],
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          mint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([contributors[0]])
+        .rpc(),
+      "EscrowFrozen"
+    );
+    await expectError(distributeEscrow(name, 0, contributors[0].publicKey), "EscrowFrozen");
+    await expectError(program.methods.setTags(name, [1]).accounts(ownerUpdate).rpc(), "EscrowFrozen");
+    await expectError(
+      program.methods
+        .closeEscrow(name)
+        .accounts({
+          escrow: pdas.escrow,
+          owner: owner.publicKey,
+          vaultAta: pdas.vault,
+          vaultAuth: pdas.vaultAuth,
+          ownerRegistry: null,
+          tombstone: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc(),
+      "EscrowFrozen"
+    );
+
+    // ✅ Thawed, the pool picks up where it left off
+    await program.methods.unfreezeAll(name).accounts(ownerUpdate).rpc();
+    await depositAll(name, [contributors[1]], [contributorAtas[1]]);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.isFalse(state.frozen);
+    assert.equal(state.contributors.length, 2);
+  });
+
+  it("🟢 Mode History: Each completed distribution records its mode", async () => {
+    const name = "escrow-mode-history";
+    const pdas = await initEscrow(name);
+    const history = (): Promise<Buffer> =>
+      program.methods.getModeHistory(name).accounts({ escrow: pdas.escrow }).view();
+
+    assert.deepEqual([...(await history())], []);
+    await depositAll(name, contributors, contributorAtas);
+    await distributeEscrow(name, 15, PublicKey.default);
+
+    // ✅ No reset exists yet, so a single-distribution escrow holds one entry
+    assert.deepEqual([...(await history())], [15]);
+  });
+
+  it("🟢 Extend Deadline: The owner reopens a pool whose deposit deadline has passed", async () => {
+    const name = "escrow-extend-deadline";
+    const passed = Math.floor(Date.now() / 1000) - 60;
+    const pdas = await initEscrow(name, { depositDeadline: passed });
+    const ownerUpdate = { escrow: pdas.escrow, owner: owner.publicKey };
+    const extend = (deadline: number) =>
+      program.methods.extendDeadline(name, new anchor.BN(deadline)).accounts(ownerUpdate).rpc();
+
+    await expectError(depositAll(name, [contributors[0]], [contributorAtas[0]]), "DepositDeadlinePassed");
+
+    await expectError(extend(passed), "DeadlineNotExtended");
+    await expectError(
+      program.methods
+        .extendDeadline(name, new anchor.BN(passed + 3600))
+        .accounts({ escrow: pdas.escrow, owner: contributors[0].publicKey })
+        .signers([contributors[0]])
+        .rpc(),
+      "Unauthorized"
+    );
+    await extend(passed + 3600);
+
+    await depositAll(name, [contributors[0]], [contributorAtas[0]]);
+    const config = await program.methods.getConfig(name).accounts({ escrow: pdas.escrow }).view();
+    assert.equal(config.depositDeadline.toNumber(), passed + 3600);
+    const state = await program.account.escrowState.fetch(pdas.escrow);
+    assert.equal(state.contributors.length, 1);
+
+    // ✅ An escrow without a deadline has nothing to extend
+    await initEscrow("escrow-no-deadline");
+    await expectError(
+      program.methods
+        .extendDeadline("escrow-no-deadline", new anchor.BN(passed + 3600))
+        .accounts({ escrow: (await deriveEscrow("escrow-no-deadline")).escrow, owner: owner.publicKey })
+        .rpc(),
+      "DeadlineNotExtended"
+    );
+  });
 });